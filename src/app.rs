@@ -1,26 +1,85 @@
-use crate::{keeper::Keeper, pulley::ChainMessage, vendor::Vendor};
-use alloy::providers::{Provider, WalletProvider};
-use tokio::sync::mpsc::UnboundedReceiver;
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    checkpoint::CheckpointHandle,
+    common::amount::Amount,
+    config::PolicyConfig,
+    control::{
+        AssetWeight, ControlRequest, EventsReport, IndexAssets, IndexStatus, PendingOrder,
+        RecentEvent, StatusReport, Trigger, VendorStatus,
+    },
+    dead_letter::{DeadLetter, DeadLetterQueue},
+    keeper::Keepers,
+    ledger::LedgerEntry,
+    metrics::Metrics,
+    order::TraderOrder,
+    pulley::{ChainEvent, ChainMessage},
+    retry::classify,
+    vendor::Vendors,
+};
+use alloy::{
+    primitives::Address,
+    providers::{Provider, WalletProvider},
+};
+use eyre::bail;
+use itertools::Itertools;
+use tokio::{
+    sync::mpsc::{Receiver, UnboundedReceiver},
+    time::{Instant, MissedTickBehavior, interval, interval_at, sleep},
+};
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{error, info, warn};
+
+/// Number of most recent events kept for control API
+const RECENT_EVENTS_CAPACITY: usize = 100;
+
+/// Interval of updating queue depth, while no events are processed
+const QUEUE_DEPTH_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct App<P>
 where
     P: Provider + WalletProvider + Clone + 'static,
 {
-    keeper: Keeper<P>,
-    vendor: Vendor<P>,
+    keepers: Keepers<P>,
+    vendors: Vendors<P>,
+    policy: PolicyConfig,
+    dead_letters: DeadLetterQueue,
+    metrics: Metrics,
+    checkpoint: CheckpointHandle,
+    paused: bool,
+    recent_events: VecDeque<RecentEvent>,
 }
 
 impl<P> App<P>
 where
     P: Provider + WalletProvider + Clone + 'static,
 {
-    pub fn new(keeper: Keeper<P>, vendor: Vendor<P>) -> Self {
-        Self { keeper, vendor }
+    pub fn new(
+        keepers: Keepers<P>,
+        vendors: Vendors<P>,
+        policy: PolicyConfig,
+        metrics: Metrics,
+        checkpoint: CheckpointHandle,
+    ) -> Self {
+        let dead_letters =
+            DeadLetterQueue::new(policy.dead_letter_file.clone(), policy.dead_letter_capacity);
+        Self {
+            keepers,
+            vendors,
+            policy,
+            dead_letters,
+            metrics,
+            checkpoint,
+            paused: false,
+            recent_events: VecDeque::new(),
+        }
     }
 
-    pub async fn process_chain_message(&mut self, message: ChainMessage) -> eyre::Result<()> {
+    pub async fn process_chain_event(&mut self, event: ChainEvent) -> eyre::Result<()> {
+        let ChainEvent { meta, message } = event;
         match message {
             ChainMessage::BuyOrder {
                 keeper,
@@ -35,18 +94,20 @@ where
                     %index_id,
                     %vendor_id,
                     %collateral,
+                    event = %meta,
                     "⛓️ ChainMessage::BuyOrder"
                 );
-                if self.keeper.get_index_id() == index_id
-                    && self.vendor.get_vendor_id() == vendor_id
+                if let Some(vendor) = self.vendors.get_mut(vendor_id)
+                    && let Some(keeper) = self.keepers.get_mut(index_id)
+                    && keeper.get_vendor_id() == vendor_id
                 {
-                    let assets = self.keeper.get_assets();
-                    self.keeper.log_trader_order(trader).await?;
-                    self.keeper.log_pending_order().await?;
-                    self.vendor.update_market(assets).await?;
-                    self.keeper.update_quote().await?;
-                    self.keeper.buy_order().await?;
-                    self.keeper.log_pending_order().await?;
+                    let assets = keeper.get_assets();
+                    keeper.log_trader_order(trader).await?;
+                    keeper.log_pending_order().await?;
+                    vendor.update_market(assets).await?;
+                    keeper.update_quote().await?;
+                    keeper.buy_order().await?;
+                    keeper.log_pending_order().await?;
                 }
             }
             ChainMessage::SellOrder {
@@ -62,18 +123,20 @@ where
                     %index_id,
                     %vendor_id,
                     %itp_amount,
+                    event = %meta,
                     "⛓️ ChainMessage::SellOrder"
                 );
-                if self.keeper.get_index_id() == index_id
-                    && self.vendor.get_vendor_id() == vendor_id
+                if let Some(vendor) = self.vendors.get_mut(vendor_id)
+                    && let Some(keeper) = self.keepers.get_mut(index_id)
+                    && keeper.get_vendor_id() == vendor_id
                 {
-                    let assets = self.keeper.get_assets();
-                    self.keeper.log_trader_order(trader).await?;
-                    self.keeper.log_pending_order().await?;
-                    self.vendor.update_market(assets).await?;
-                    self.keeper.update_quote().await?;
-                    self.keeper.sell_order().await?;
-                    self.keeper.log_pending_order().await?;
+                    let assets = keeper.get_assets();
+                    keeper.log_trader_order(trader).await?;
+                    keeper.log_pending_order().await?;
+                    vendor.update_market(assets).await?;
+                    keeper.update_quote().await?;
+                    keeper.sell_order().await?;
+                    keeper.log_pending_order().await?;
                 }
             }
             ChainMessage::Acquisition {
@@ -91,10 +154,18 @@ where
                     %remain,
                     %spent,
                     %minted,
+                    event = %meta,
                     "⛓️ ChainMessage::Acquisition"
                 );
-                if self.vendor.get_vendor_id() == vendor_id {
-                    self.vendor.update_supply().await?;
+                if let Some(vendor) = self.vendors.get_mut(vendor_id) {
+                    vendor.record_ledger(LedgerEntry::Acquisition {
+                        index_id,
+                        tx_hash: meta.tx_hash,
+                        log_index: meta.log_index,
+                        spent: Amount::from_u128_raw(spent),
+                        minted: Amount::from_u128_raw(minted),
+                    });
+                    vendor.update_supply().await?;
                 }
             }
             ChainMessage::Disposal {
@@ -112,10 +183,18 @@ where
                     %remain,
                     %burned,
                     %gains,
+                    event = %meta,
                     "⛓️ ChainMessage::Disposal"
                 );
-                if self.vendor.get_vendor_id() == vendor_id {
-                    self.vendor.update_supply().await?;
+                if let Some(vendor) = self.vendors.get_mut(vendor_id) {
+                    vendor.record_ledger(LedgerEntry::Disposal {
+                        index_id,
+                        tx_hash: meta.tx_hash,
+                        log_index: meta.log_index,
+                        burned: Amount::from_u128_raw(burned),
+                        gains: Amount::from_u128_raw(gains),
+                    });
+                    vendor.update_supply().await?;
                 }
             }
             ChainMessage::AcquisitionClaim {
@@ -133,15 +212,20 @@ where
                     %vendor_id,
                     %remain,
                     %spent,
+                    event = %meta,
                     "⛓️ ChainMessage::AcquisitionClaim"
                 );
-                if 100 < remain {
-                    let assets = self.keeper.get_assets();
-                    self.vendor.update_market(assets).await?;
-                    self.keeper.update_quote().await?;
-                    self.keeper.buy_order().await?;
-                    self.keeper.log_pending_order().await?;
-                    self.keeper.log_trader_order(trader).await?;
+                if self.policy.min_remain < remain
+                    && let Some(vendor) = self.vendors.get_mut(vendor_id)
+                    && let Some(keeper) = self.keepers.get_mut(index_id)
+                    && keeper.get_vendor_id() == vendor_id
+                {
+                    let assets = keeper.get_assets();
+                    vendor.update_market(assets).await?;
+                    keeper.update_quote().await?;
+                    keeper.buy_order().await?;
+                    keeper.log_pending_order().await?;
+                    keeper.log_trader_order(trader).await?;
                 }
             }
             ChainMessage::DisposalClaim {
@@ -159,37 +243,406 @@ where
                     %vendor_id,
                     %itp_remain,
                     %itp_burned,
+                    event = %meta,
                     "⛓️ ChainMessage::DisposalClaim"
                 );
-                if 100 < itp_remain {
-                    let assets = self.keeper.get_assets();
-                    self.vendor.update_market(assets).await?;
-                    self.keeper.update_quote().await?;
-                    self.keeper.sell_order().await?;
-                    self.keeper.log_pending_order().await?;
-                    self.keeper.log_trader_order(trader).await?;
+                if self.policy.min_remain < itp_remain
+                    && let Some(vendor) = self.vendors.get_mut(vendor_id)
+                    && let Some(keeper) = self.keepers.get_mut(index_id)
+                    && keeper.get_vendor_id() == vendor_id
+                {
+                    let assets = keeper.get_assets();
+                    vendor.update_market(assets).await?;
+                    keeper.update_quote().await?;
+                    keeper.sell_order().await?;
+                    keeper.log_pending_order().await?;
+                    keeper.log_trader_order(trader).await?;
+                }
+            }
+            ChainMessage::Reorg { block_number } => {
+                warn!(
+                    %block_number,
+                    event = %meta,
+                    "⛓️ ChainMessage::Reorg"
+                );
+                // Orders from orphaned block may be gone, or included again in
+                // another block, so we re-sync from the canonical chain
+                self.reconcile().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Process event, retrying on failure according to class of the error
+    ///
+    /// Events that keep failing are put into dead letter queue, so that they
+    /// don't stop processing of later events. Orders they were about are
+    /// still picked up by reconciler.
+    ///
+    /// Returns whether event was processed successfully.
+    pub async fn process_chain_event_with_retry(
+        &mut self,
+        event: ChainEvent,
+        cancel: &CancellationToken,
+    ) -> eyre::Result<bool> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let err = match self.process_chain_event(event.clone()).await {
+                Ok(()) => return Ok(true),
+                Err(err) => err,
+            };
+
+            let class = classify(&err);
+            let Some(backoff) = self.policy.retry.policy(class).backoff(attempt) else {
+                error!(
+                    event = %event.meta,
+                    %class,
+                    %attempt,
+                    "💀 Failed to process event: {:?}",
+                    err
+                );
+                self.dead_letters
+                    .push(DeadLetter::new(&event, class, attempt, &err));
+                return Ok(false);
+            };
+
+            warn!(
+                event = %event.meta,
+                %class,
+                %attempt,
+                "Failed to process event, retrying in {:?}: {:?}",
+                backoff,
+                err
+            );
+
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(false),
+                _ = sleep(backoff) => {}
+            }
+        }
+    }
+
+    /// Process orders left pending on-chain, e.g. placed while we were down
+    ///
+    /// Quotes of all Indexes with pending orders are updated together. Orders
+    /// of each Index are processed again, as long as collateral or locked ITP
+    /// remains and each pass makes progress.
+    pub async fn reconcile(&mut self) -> eyre::Result<()> {
+        let min_remain = self.policy.min_remain;
+        let mut pending = Vec::new();
+        for keeper in self.keepers.iter() {
+            let orders = keeper.get_trader_orders().await?;
+            let (pending_buy, pending_sell) = pending_traders(&orders, min_remain);
+
+            info!(
+                index_id = %keeper.get_index_id(),
+                traders = %orders.len(),
+                pending_buy = %pending_buy.len(),
+                pending_sell = %pending_sell.len(),
+                "🔁 Reconcile"
+            );
+
+            if !pending_buy.is_empty() || !pending_sell.is_empty() {
+                pending.push((keeper.get_index_id(), orders));
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let index_ids = pending.iter().map(|(index_id, _)| *index_id).collect_vec();
+        self.update_market(&index_ids).await?;
+        self.keepers.update_quotes(&index_ids).await?;
+
+        for (index_id, mut orders) in pending {
+            let Some(keeper) = self.keepers.get_mut(index_id) else {
+                continue;
+            };
+            loop {
+                let (pending_buy, pending_sell) = pending_traders(&orders, min_remain);
+                if pending_buy.is_empty() && pending_sell.is_empty() {
+                    break;
+                }
+                if !pending_buy.is_empty() {
+                    keeper.buy_order().await?;
+                }
+                if !pending_sell.is_empty() {
+                    keeper.sell_order().await?;
                 }
+
+                for trader in pending_buy.into_iter().chain(pending_sell).unique() {
+                    keeper.log_trader_order(trader).await?;
+                }
+
+                let next_orders = keeper.get_trader_orders().await?;
+                if next_orders == orders {
+                    warn!(%index_id, "🔁 Reconcile made no progress, leaving orders pending");
+                    break;
+                }
+                orders = next_orders;
             }
         }
+
         Ok(())
     }
 
+    /// Update market data of assets of given Indexes, by Vendors serving them
+    async fn update_market(&mut self, index_ids: &[u128]) -> eyre::Result<()> {
+        for vendor in self.vendors.iter_mut() {
+            let vendor_index_ids = self
+                .keepers
+                .get_vendor_index_ids(vendor.get_vendor_id())
+                .into_iter()
+                .filter(|x| index_ids.contains(x))
+                .collect_vec();
+            if !vendor_index_ids.is_empty() {
+                let assets = self.keepers.get_assets(&vendor_index_ids);
+                vendor.update_market(&assets).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run operation of Keeper or Vendor requested via control API
+    ///
+    /// Keeper operations run for given Index, or for all of them.
+    pub async fn trigger(&mut self, trigger: Trigger, index_id: Option<u128>) -> eyre::Result<()> {
+        info!(%trigger, ?index_id, "🕹️ Trigger");
+        let index_ids = match index_id {
+            Some(index_id) if self.keepers.get(index_id).is_none() => {
+                bail!("Index {} is not kept", index_id)
+            }
+            Some(index_id) => vec![index_id],
+            None => self.keepers.get_index_ids(),
+        };
+        match trigger {
+            Trigger::UpdateMarket => self.update_market(&index_ids).await?,
+            Trigger::UpdateQuote => self.keepers.update_quotes(&index_ids).await?,
+            Trigger::BuyOrder | Trigger::SellOrder => {
+                for index_id in index_ids {
+                    if let Some(keeper) = self.keepers.get_mut(index_id) {
+                        match trigger {
+                            Trigger::BuyOrder => keeper.buy_order().await?,
+                            _ => keeper.sell_order().await?,
+                        }
+                    }
+                }
+            }
+            Trigger::UpdateSupply => {
+                for vendor in self.vendors.iter_mut() {
+                    vendor.update_supply().await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Orders of traders with collateral or locked ITP above `min_remain`
+    pub async fn get_pending_orders(&self) -> eyre::Result<Vec<PendingOrder>> {
+        let mut pending = Vec::new();
+        for keeper in self.keepers.iter() {
+            let orders = keeper.get_trader_orders().await?;
+            pending.extend(
+                orders
+                    .into_iter()
+                    .filter(|TraderOrder { order, .. }| {
+                        order.has_pending_buy(self.policy.min_remain)
+                            || order.has_pending_sell(self.policy.min_remain)
+                    })
+                    .map(|TraderOrder { trader, order }| PendingOrder {
+                        index_id: keeper.get_index_id(),
+                        trader,
+                        order,
+                    }),
+            );
+        }
+        Ok(pending)
+    }
+
+    pub fn get_status(&self) -> StatusReport {
+        StatusReport {
+            paused: self.paused,
+            keeper_address: self.keepers.get_address(),
+            vendors: self
+                .vendors
+                .iter()
+                .map(|vendor| VendorStatus {
+                    vendor_id: vendor.get_vendor_id(),
+                    vendor_address: vendor.get_address(),
+                })
+                .collect(),
+            indexes: self
+                .keepers
+                .iter()
+                .map(|keeper| IndexStatus {
+                    index_id: keeper.get_index_id(),
+                    vendor_id: keeper.get_vendor_id(),
+                    vault_address: keeper.get_vault_address(),
+                    custody_address: keeper.get_custody_address(),
+                    collateral_address: keeper.get_collateral_address(),
+                })
+                .collect(),
+            dead_letters: self.dead_letters.len(),
+        }
+    }
+
+    pub fn get_index_assets(&self) -> Vec<IndexAssets> {
+        self.keepers
+            .iter()
+            .map(|keeper| IndexAssets {
+                index_id: keeper.get_index_id(),
+                assets: keeper
+                    .get_assets()
+                    .data
+                    .iter()
+                    .zip(&keeper.get_weights().data)
+                    .map(|(asset, weight)| AssetWeight {
+                        asset: *asset,
+                        weight: *weight,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    async fn handle_control(&mut self, request: ControlRequest) {
+        // Replies are dropped when HTTP client is gone, which is fine
+        match request {
+            ControlRequest::Status(reply) => {
+                let _ = reply.send(self.get_status());
+            }
+            ControlRequest::Index(reply) => {
+                let _ = reply.send(self.get_index_assets());
+            }
+            ControlRequest::Events(reply) => {
+                let _ = reply.send(EventsReport {
+                    recent: self.recent_events.iter().cloned().collect(),
+                    dead_letters: self.dead_letters.letters().cloned().collect(),
+                });
+            }
+            ControlRequest::PendingOrders(reply) => {
+                let _ = reply.send(self.get_pending_orders().await);
+            }
+            ControlRequest::Trigger(trigger, index_id, reply) => {
+                let result = self.trigger(trigger, index_id).await;
+                if let Err(err) = &result {
+                    warn!(%trigger, "Failed to run trigger: {:?}", err);
+                }
+                let _ = reply.send(result);
+            }
+            ControlRequest::SetPaused(paused, reply) => {
+                if self.paused != paused {
+                    info!(%paused, "⏯️ Event processing {}", if paused { "paused" } else { "resumed" });
+                }
+                let _ = reply.send(std::mem::replace(&mut self.paused, paused));
+            }
+        }
+    }
+
+    /// Process events until cancelled
+    ///
+    /// Control requests are handled in between events. While paused, events
+    /// wait in the channel and reconciler doesn't run.
     pub async fn run(
         &mut self,
-        mut recv: UnboundedReceiver<ChainMessage>,
+        mut recv: UnboundedReceiver<ChainEvent>,
+        mut control: Receiver<ControlRequest>,
         cancel: CancellationToken,
     ) -> eyre::Result<()> {
         info!("✅ App loop started...");
+
+        if let Err(err) = self.reconcile().await {
+            warn!("Failed to reconcile pending orders: {:?}", err);
+        }
+
+        let reconcile_period = self.policy.reconcile_interval();
+        let mut reconcile_interval = interval_at(
+            Instant::now() + reconcile_period.unwrap_or_default(),
+            reconcile_period.unwrap_or(Duration::from_secs(1)),
+        );
+        reconcile_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut fill_interval = interval(self.vendors.get_fill_poll_interval());
+        fill_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut queue_depth_interval = interval(QUEUE_DEPTH_INTERVAL);
+        queue_depth_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         loop {
+            // Events keep arriving while paused or busy, so backlog is reported on every iteration
+            self.metrics.set_queue_depth(recv.len());
+
             tokio::select! {
                 _ = cancel.cancelled() => {
                     info!("App loop complete.");
                     return Ok(())
                 }
-                Some(message) = recv.recv() => {
-                    self.process_chain_message(message).await?;
+                Some(request) = control.recv() => {
+                    self.handle_control(request).await;
+                }
+                Some(event) = recv.recv(), if !self.paused => {
+                    let message = event.message.name();
+                    self.metrics.inc_chain_message(message);
+
+                    let started = Instant::now();
+                    let processed = self.process_chain_event_with_retry(event.clone(), &cancel).await?;
+                    if cancel.is_cancelled() {
+                        // Event may be left unprocessed, so it must be backfilled after restart
+                        continue;
+                    }
+                    if let Err(err) = self.checkpoint.processed() {
+                        // Checkpoint is saved again on next event
+                        error!("Failed to save checkpoint: {:?}", err);
+                    }
+                    let elapsed = started.elapsed();
+                    self.metrics.observe_handler_duration(message, elapsed);
+
+                    // Transactions of the event were mined by the time it was processed
+                    if processed && let Some(timestamp) = event.meta.timestamp {
+                        let emitted = UNIX_EPOCH + Duration::from_secs(timestamp);
+                        let latency = SystemTime::now().duration_since(emitted).unwrap_or_default();
+                        self.metrics.observe_event_latency(message, latency);
+                    }
+                    if let Some(block_number) = event.meta.block_number {
+                        self.metrics.set_last_block(block_number);
+                    }
+
+                    self.recent_events.push_back(RecentEvent::new(&event, elapsed.as_millis()));
+                    while RECENT_EVENTS_CAPACITY < self.recent_events.len() {
+                        self.recent_events.pop_front();
+                    }
+                }
+                _ = reconcile_interval.tick(), if reconcile_period.is_some() && !self.paused => {
+                    if let Err(err) = self.reconcile().await {
+                        warn!("Failed to reconcile pending orders: {:?}", err);
+                    }
+                }
+                _ = queue_depth_interval.tick() => {}
+                _ = fill_interval.tick(), if self.vendors.has_pending_hedges() => {
+                    for vendor in self.vendors.iter_mut().filter(|x| x.has_pending_hedges()) {
+                        if let Err(err) = vendor.poll_fills().await {
+                            warn!(vendor_id = %vendor.get_vendor_id(), "Failed to process fills: {:?}", err);
+                        }
+                    }
                 }
             }
         }
     }
 }
+
+/// Traders with pending Buy and Sell orders
+fn pending_traders(orders: &[TraderOrder], min_remain: u128) -> (Vec<Address>, Vec<Address>) {
+    let mut pending_buy = Vec::new();
+    let mut pending_sell = Vec::new();
+    for TraderOrder { trader, order } in orders {
+        if order.has_pending_buy(min_remain) {
+            pending_buy.push(*trader);
+        }
+        if order.has_pending_sell(min_remain) {
+            pending_sell.push(*trader);
+        }
+    }
+    (pending_buy, pending_sell)
+}
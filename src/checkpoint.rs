@@ -0,0 +1,361 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use eyre::Context;
+use tracing::debug;
+
+use crate::config::PulleyConfig;
+
+/// Last block, whose events were all processed
+///
+/// Persisted to file (when configured), so that after restart logs emitted
+/// while Conveyor was down can be backfilled. Block partially processed is
+/// persisted as `block:log_index` of its last processed log, so that logs
+/// already handled are not handled again when the block is backfilled.
+pub struct Checkpoint {
+    path: Option<PathBuf>,
+    last_block: Option<u64>,
+    /// Block following `last_block` and index of its last processed log
+    last_log: Option<(u64, u64)>,
+}
+
+impl Checkpoint {
+    pub fn load(path: Option<PathBuf>) -> eyre::Result<Self> {
+        let last_log = match &path {
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read checkpoint: {}", path.display()))?;
+                Some(
+                    parse_checkpoint(text.trim())
+                        .with_context(|| format!("Invalid checkpoint: {}", path.display()))?,
+                )
+            }
+            _ => None,
+        };
+
+        Ok(match last_log {
+            Some((block, Some(log_index))) => Self {
+                path,
+                last_block: block.checked_sub(1),
+                last_log: Some((block, log_index)),
+            },
+            Some((block, None)) => Self {
+                path,
+                last_block: Some(block),
+                last_log: None,
+            },
+            None => Self {
+                path,
+                last_block: None,
+                last_log: None,
+            },
+        })
+    }
+
+    pub fn last_block(&self) -> Option<u64> {
+        self.last_block
+    }
+
+    /// Last processed log of the block following `last_block`
+    pub fn last_log(&self) -> Option<(u64, u64)> {
+        self.last_log
+    }
+
+    /// Move checkpoint forward (never backwards) and persist it
+    pub fn advance(&mut self, block: u64) -> eyre::Result<()> {
+        if self.last_block.is_some_and(|last| block <= last) {
+            return Ok(());
+        }
+
+        self.save(&block.to_string())?;
+
+        self.last_block = Some(block);
+        self.last_log = None;
+        Ok(())
+    }
+
+    /// Move checkpoint forward to given log (never backwards) and persist it
+    pub fn advance_log(&mut self, block: u64, log_index: u64) -> eyre::Result<()> {
+        if self.last_block.is_some_and(|last| block <= last)
+            || self.last_log.is_some_and(|last| (block, log_index) <= last)
+        {
+            return Ok(());
+        }
+
+        self.save(&format!("{}:{}", block, log_index))?;
+
+        self.last_block = block.checked_sub(1).max(self.last_block);
+        self.last_log = Some((block, log_index));
+        Ok(())
+    }
+
+    fn save(&self, text: &str) -> eyre::Result<()> {
+        if let Some(path) = &self.path {
+            let tmp_path = path.with_extension("tmp");
+            std::fs::write(&tmp_path, text)
+                .with_context(|| format!("Failed to write checkpoint: {}", tmp_path.display()))?;
+            std::fs::rename(&tmp_path, path)
+                .with_context(|| format!("Failed to write checkpoint: {}", path.display()))?;
+            debug!(checkpoint = %text, "Checkpoint saved");
+        }
+        Ok(())
+    }
+
+    /// Start over from given block, e.g. when overridden on command-line
+    pub fn reset(&mut self, block: u64) -> eyre::Result<()> {
+        self.last_block = None;
+        self.last_log = None;
+        self.advance(block)
+    }
+}
+
+/// Block, and index of the last processed log when block is only partially processed
+fn parse_checkpoint(text: &str) -> eyre::Result<(u64, Option<u64>)> {
+    let (block, log_index) = match text.split_once(':') {
+        Some((block, log_index)) => (block, Some(log_index.parse()?)),
+        None => (text, None),
+    };
+    Ok((block.parse()?, log_index))
+}
+
+/// Checkpoint shared by Pulley forwarding events and App processing them
+///
+/// Block forwarded by Pulley is persisted only once App processed all events
+/// sent before it, so that events still queued or held while paused are
+/// backfilled again after restart.
+#[derive(Clone)]
+pub struct CheckpointHandle {
+    inner: Arc<Mutex<Progress>>,
+}
+
+struct Progress {
+    checkpoint: Checkpoint,
+    last_forwarded: Option<u64>,
+    sent: u64,
+    processed: u64,
+    /// Blocks and logs forwarded, each with number of events sent before it
+    pending: VecDeque<(u64, Mark)>,
+}
+
+/// How far Pulley has forwarded logs
+#[derive(Debug, Clone, Copy)]
+enum Mark {
+    Block(u64),
+    Log(u64, u64),
+}
+
+impl Mark {
+    fn advance(self, checkpoint: &mut Checkpoint) -> eyre::Result<()> {
+        match self {
+            Mark::Block(block) => checkpoint.advance(block),
+            Mark::Log(block, log_index) => checkpoint.advance_log(block, log_index),
+        }
+    }
+}
+
+impl CheckpointHandle {
+    pub fn new(checkpoint: Checkpoint) -> Self {
+        let last_forwarded = checkpoint.last_block();
+        Self {
+            inner: Arc::new(Mutex::new(Progress {
+                checkpoint,
+                last_forwarded,
+                sent: 0,
+                processed: 0,
+                pending: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Load checkpoint of Pulley, starting over from `from_block` when set
+    pub fn load(config: &PulleyConfig) -> eyre::Result<Self> {
+        let mut checkpoint = Checkpoint::load(config.checkpoint_file.clone())?;
+        if let Some(from_block) = config.from_block {
+            checkpoint.reset(from_block.saturating_sub(1))?;
+        }
+        Ok(Self::new(checkpoint))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Progress> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Last block, whose events were all processed
+    pub fn last_block(&self) -> Option<u64> {
+        self.lock().checkpoint.last_block()
+    }
+
+    /// Last processed log of the block following `last_block`
+    pub fn last_log(&self) -> Option<(u64, u64)> {
+        self.lock().checkpoint.last_log()
+    }
+
+    /// Last block, whose logs were all forwarded
+    pub fn last_forwarded(&self) -> Option<u64> {
+        self.lock().last_forwarded
+    }
+
+    /// Pulley sent event to App
+    pub fn sent(&self) {
+        self.lock().sent += 1;
+    }
+
+    /// Pulley forwarded all logs up to given block
+    pub fn forwarded(&self, block: u64) -> eyre::Result<()> {
+        let mut progress = self.lock();
+        if progress.last_forwarded.is_some_and(|last| block <= last) {
+            return Ok(());
+        }
+        progress.last_forwarded = Some(block);
+        progress.mark(Mark::Block(block))
+    }
+
+    /// Pulley forwarded given log, and all logs before it
+    pub fn forwarded_log(&self, block: u64, log_index: u64) -> eyre::Result<()> {
+        let mut progress = self.lock();
+        if let Some(previous) = block.checked_sub(1)
+            && progress.last_forwarded.is_none_or(|last| last < previous)
+        {
+            progress.last_forwarded = Some(previous);
+        }
+        progress.mark(Mark::Log(block, log_index))
+    }
+
+    /// App processed next event
+    ///
+    /// Block, which failed to persist, is kept and saved again on next event.
+    pub fn processed(&self) -> eyre::Result<()> {
+        let mut progress = self.lock();
+        progress.processed += 1;
+
+        let mut mark = None;
+        while let Some((sent, _)) = progress.pending.front()
+            && *sent <= progress.processed
+        {
+            mark = progress.pending.pop_front().map(|(_, mark)| mark);
+        }
+
+        let Some(mark) = mark else {
+            return Ok(());
+        };
+        let result = mark.advance(&mut progress.checkpoint);
+        if result.is_err() {
+            let processed = progress.processed;
+            progress.pending.push_front((processed, mark));
+        }
+        result
+    }
+}
+
+impl Progress {
+    /// Persist mark once App processed all events sent before it
+    fn mark(&mut self, mark: Mark) -> eyre::Result<()> {
+        if self.processed == self.sent {
+            mark.advance(&mut self.checkpoint)
+        } else {
+            let sent = self.sent;
+            self.pending.push_back((sent, mark));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint() {
+        let path = std::env::temp_dir().join(format!("conveyor-{}.checkpoint", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut checkpoint = Checkpoint::load(Some(path.clone())).unwrap();
+        assert_eq!(checkpoint.last_block(), None);
+
+        checkpoint.advance(10).unwrap();
+        checkpoint.advance(5).unwrap();
+        assert_eq!(checkpoint.last_block(), Some(10));
+
+        let mut checkpoint = Checkpoint::load(Some(path.clone())).unwrap();
+        assert_eq!(checkpoint.last_block(), Some(10));
+
+        checkpoint.reset(3).unwrap();
+        assert_eq!(
+            Checkpoint::load(Some(path.clone())).unwrap().last_block(),
+            Some(3)
+        );
+
+        // Block partially processed is resumed after its last processed log
+        checkpoint.advance_log(5, 2).unwrap();
+        checkpoint.advance_log(5, 1).unwrap();
+        let mut checkpoint = Checkpoint::load(Some(path.clone())).unwrap();
+        assert_eq!(checkpoint.last_block(), Some(4));
+        assert_eq!(checkpoint.last_log(), Some((5, 2)));
+
+        checkpoint.advance(5).unwrap();
+        checkpoint.advance_log(5, 3).unwrap();
+        let checkpoint = Checkpoint::load(Some(path.clone())).unwrap();
+        assert_eq!(checkpoint.last_block(), Some(5));
+        assert_eq!(checkpoint.last_log(), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_handle() {
+        let handle = CheckpointHandle::new(Checkpoint::load(None).unwrap());
+
+        // Nothing in flight, so block is complete as soon as it is forwarded
+        handle.forwarded(5).unwrap();
+        assert_eq!(handle.last_block(), Some(5));
+
+        handle.sent();
+        handle.forwarded(6).unwrap();
+        handle.sent();
+        handle.forwarded(7).unwrap();
+        assert_eq!(handle.last_forwarded(), Some(7));
+        assert_eq!(handle.last_block(), Some(5));
+
+        handle.processed().unwrap();
+        assert_eq!(handle.last_block(), Some(6));
+
+        handle.processed().unwrap();
+        assert_eq!(handle.last_block(), Some(7));
+
+        // Log is complete once App processed it
+        handle.sent();
+        handle.forwarded_log(9, 4).unwrap();
+        assert_eq!(handle.last_forwarded(), Some(8));
+        assert_eq!(handle.last_log(), None);
+
+        handle.processed().unwrap();
+        assert_eq!(handle.last_block(), Some(8));
+        assert_eq!(handle.last_log(), Some((9, 4)));
+    }
+
+    #[test]
+    fn test_checkpoint_handle_retry() {
+        let dir = std::env::temp_dir().join(format!("conveyor-{}-retry", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("checkpoint");
+
+        let handle = CheckpointHandle::new(Checkpoint::load(Some(path.clone())).unwrap());
+        handle.sent();
+        handle.forwarded(5).unwrap();
+
+        // Directory is missing, so checkpoint cannot be saved
+        assert!(handle.processed().is_err());
+        assert_eq!(handle.last_block(), None);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        handle.sent();
+        handle.processed().unwrap();
+        assert_eq!(handle.last_block(), Some(5));
+        assert_eq!(Checkpoint::load(Some(path)).unwrap().last_block(), Some(5));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, fmt::Display, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+/// Vendor calls submitted in chunks of assets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    SubmitAssets,
+    SubmitMargin,
+    SubmitMarketData,
+    SubmitSupply,
+}
+
+impl Display for CallKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CallKind::SubmitAssets => "submitAssets",
+            CallKind::SubmitMargin => "submitMargin",
+            CallKind::SubmitMarketData => "submitMarketData",
+            CallKind::SubmitSupply => "submitSupply",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChunkingConfig {
+    /// Derive chunk size of each call from gas estimates (otherwise always use `chunk_size`)
+    pub auto: bool,
+    /// Maximum gas of single chunk transaction (block gas limit when not set)
+    pub gas_ceiling: Option<u64>,
+    /// Part of the gas ceiling kept unused, in case estimates are off
+    pub gas_headroom_percent: u64,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            gas_ceiling: None,
+            gas_headroom_percent: 20,
+        }
+    }
+}
+
+impl ChunkingConfig {
+    /// Gas available to single chunk, given block gas limit
+    pub fn gas_budget(&self, block_gas_limit: u64) -> u64 {
+        let ceiling = self
+            .gas_ceiling
+            .unwrap_or(block_gas_limit)
+            .min(block_gas_limit);
+        ceiling - ceiling * self.gas_headroom_percent.min(100) / 100
+    }
+}
+
+/// Linear gas model of a chunked call: `base + per_item * items`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasModel {
+    pub base: u64,
+    pub per_item: u64,
+}
+
+impl GasModel {
+    /// Fit model through gas estimates of two chunks of different sizes
+    pub fn fit(small: (usize, u64), large: (usize, u64)) -> Option<Self> {
+        let ((n1, g1), (n2, g2)) = (small, large);
+        if n2 <= n1 {
+            return None;
+        }
+        let per_item = g2.saturating_sub(g1).div_ceil((n2 - n1) as u64).max(1);
+        let base = g1.saturating_sub(per_item * n1 as u64);
+        Some(Self { base, per_item })
+    }
+
+    /// Largest number of items, whose gas fits into given budget
+    pub fn max_items(&self, gas_budget: u64) -> usize {
+        (gas_budget.saturating_sub(self.base) / self.per_item) as usize
+    }
+}
+
+/// Chunk size of each call kind, shrunk whenever chunk runs out of gas
+///
+/// Shrunk size grows back toward the size derived from gas estimates,
+/// as chunks of that size succeed. No size exceeds `max_chunk_size`.
+pub struct ChunkSizes {
+    max_chunk_size: usize,
+    sizes: HashMap<CallKind, usize>,
+    /// Size derived from gas estimates, which shrunk size grows back to
+    bounds: HashMap<CallKind, usize>,
+}
+
+impl ChunkSizes {
+    pub fn new(max_chunk_size: usize) -> Self {
+        Self {
+            max_chunk_size: max_chunk_size.max(1),
+            sizes: HashMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    pub fn max_chunk_size(&self) -> usize {
+        self.max_chunk_size
+    }
+
+    pub fn get(&self, kind: CallKind) -> Option<usize> {
+        self.sizes.get(&kind).copied()
+    }
+
+    pub fn set(&mut self, kind: CallKind, size: usize) -> usize {
+        let size = size.clamp(1, self.max_chunk_size);
+        self.sizes.insert(kind, size);
+        self.bounds.insert(kind, size);
+        size
+    }
+
+    /// Halve chunk size after a chunk of given size ran out of gas
+    pub fn shrink(&mut self, kind: CallKind, failed_size: usize) -> Option<usize> {
+        if failed_size <= 1 {
+            return None;
+        }
+        let size = self.get(kind).unwrap_or(failed_size).min(failed_size / 2);
+        let size = size.clamp(1, self.max_chunk_size);
+        self.sizes.insert(kind, size);
+        Some(size)
+    }
+
+    /// Double chunk size (up to its bound) after a chunk of given size succeeded
+    pub fn grow(&mut self, kind: CallKind, succeeded_size: usize) -> Option<usize> {
+        let size = self.get(kind)?;
+        let bound = self
+            .bounds
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.max_chunk_size);
+        if succeeded_size < size || bound <= size {
+            return None;
+        }
+        let size = size.saturating_mul(2).min(bound);
+        self.sizes.insert(kind, size);
+        Some(size)
+    }
+}
+
+/// Split range into consecutive ranges of at most given size
+pub fn split_range(range: Range<usize>, size: usize) -> Vec<Range<usize>> {
+    let size = size.max(1);
+    range
+        .clone()
+        .step_by(size)
+        .map(|start| start..(start + size).min(range.end))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gas_model() {
+        let model = GasModel::fit((50, 1_100_000), (100, 2_100_000)).unwrap();
+        assert_eq!(
+            model,
+            GasModel {
+                base: 100_000,
+                per_item: 20_000
+            }
+        );
+        assert_eq!(model.max_items(30_000_000), 1495);
+
+        let config = ChunkingConfig::default();
+        assert_eq!(config.gas_budget(30_000_000), 24_000_000);
+        assert_eq!(model.max_items(config.gas_budget(30_000_000)), 1195);
+    }
+
+    #[test]
+    fn test_chunk_sizes() {
+        let mut sizes = ChunkSizes::new(500);
+        assert_eq!(sizes.set(CallKind::SubmitSupply, 1195), 500);
+        assert_eq!(sizes.shrink(CallKind::SubmitSupply, 500), Some(250));
+        assert_eq!(sizes.shrink(CallKind::SubmitSupply, 101), Some(50));
+        assert_eq!(sizes.shrink(CallKind::SubmitSupply, 1), None);
+        assert_eq!(sizes.get(CallKind::SubmitMargin), None);
+
+        // Shrunk size grows back to the bound, after chunks of its size succeed
+        assert_eq!(sizes.grow(CallKind::SubmitSupply, 20), None);
+        assert_eq!(sizes.grow(CallKind::SubmitSupply, 50), Some(100));
+        assert_eq!(sizes.grow(CallKind::SubmitSupply, 100), Some(200));
+        assert_eq!(sizes.grow(CallKind::SubmitSupply, 200), Some(400));
+        assert_eq!(sizes.grow(CallKind::SubmitSupply, 400), Some(500));
+        assert_eq!(sizes.grow(CallKind::SubmitSupply, 500), None);
+        assert_eq!(sizes.grow(CallKind::SubmitMargin, 500), None);
+
+        assert_eq!(split_range(0..7, 3), vec![0..3, 3..6, 6..7]);
+        assert_eq!(split_range(4..6, 5), vec![4..6]);
+    }
+}
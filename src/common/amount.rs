@@ -64,7 +64,7 @@ impl Amount {
     pub const ONE: Amount = Amount(Self::SCALE);
     pub const TWO: Amount = Amount(2 * Self::SCALE);
     pub const FOUR: Amount = Amount(4 * Self::SCALE);
-    pub const SCALE: u128 = 1_000_000_000__000_000_000;
+    pub const SCALE: u128 = 1_000_000_000_000_000_000;
     pub const SCALE_SQRT: u128 = 1_000_000_000;
     pub const DECIMALS: usize = 18;
 
@@ -214,6 +214,67 @@ impl core::fmt::Display for Amount {
     }
 }
 
+impl core::str::FromStr for Amount {
+    type Err = eyre::Report;
+
+    /// Parse decimal representation, e.g. `100`, `0.25` or `1.000000000000000001`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (integral, fraction) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+
+        if integral.is_empty() && fraction.is_empty() {
+            eyre::bail!("Empty amount");
+        }
+
+        if Amount::DECIMALS < fraction.len() {
+            eyre::bail!("Too many decimals in amount: {}", s);
+        }
+
+        let integral = if integral.is_empty() {
+            0
+        } else {
+            integral.parse::<u128>()?
+        };
+
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            let padded = format!("{:0<width$}", fraction, width = Amount::DECIMALS);
+            padded.parse::<u128>()?
+        };
+
+        let result = convert_from_u128(integral) * convert_from_u128(Self::SCALE)
+            + convert_from_u128(fraction);
+
+        Self::try_from_u256(result).ok_or_else(|| eyre::eyre!("Amount overflow: {}", s))
+    }
+}
+
+impl serde::Serialize for Amount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Amount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            Integer(u64),
+            Float(f64),
+        }
+
+        let text = match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => text,
+            Repr::Integer(value) => value.to_string(),
+            Repr::Float(value) => value.to_string(),
+        };
+
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl core::fmt::Debug for Amount {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "amount!({})", self)
@@ -221,6 +282,7 @@ impl core::fmt::Debug for Amount {
 }
 
 #[cfg(test)]
+#[allow(clippy::inconsistent_digit_grouping, clippy::zero_prefixed_literal)]
 mod test {
     use super::*;
 
@@ -291,4 +353,29 @@ mod test {
             Amount::from_u128_with_scale(2, 1).is_less_than(&Amount::from_u128_with_scale(1, 0))
         );
     }
+
+    #[test]
+    fn test_amount_from_str() {
+        do_test_amount("1".parse().unwrap(), Amount::ONE);
+        do_test_amount(
+            "1.50".parse().unwrap(),
+            Amount::from_u128_with_scale(1_50, 2),
+        );
+        do_test_amount(
+            ".25".parse().unwrap(),
+            Amount::from_u128_with_scale(0_25, 2),
+        );
+        do_test_amount("0.000000000000000001".parse().unwrap(), Amount::EPSILON);
+        do_test_amount(
+            Amount::from_u128_with_scale(123_456, 3)
+                .to_string()
+                .parse()
+                .unwrap(),
+            Amount::from_u128_with_scale(123_456, 3),
+        );
+
+        assert!("".parse::<Amount>().is_err());
+        assert!("1.2.3".parse::<Amount>().is_err());
+        assert!("0.0000000000000000001".parse::<Amount>().is_err());
+    }
 }
@@ -6,6 +6,10 @@ pub const DEMAND_SHORT_OFFSET: usize = 1;
 pub const SUPPLY_LONG_OFFSET: usize = 0;
 pub const SUPPLY_SHORT_OFFSET: usize = 1;
 
+// Delta vector
+pub const DELTA_LONG_OFFSET: usize = 0;
+pub const DELTA_SHORT_OFFSET: usize = 1;
+
 // Order
 pub const ORDER_COLLATERAL_OFFSET: usize = 0;
 pub const ORDER_SPENT_OFFSET: usize = 1;
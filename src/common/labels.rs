@@ -1,5 +1,6 @@
 use crate::common::uint::{read_u128, write_u128};
 
+#[derive(Default)]
 pub struct Labels {
     pub data: Vec<u128>,
 }
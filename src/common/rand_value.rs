@@ -16,6 +16,7 @@ impl ValueGen {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Amount {
         let value = self.rng.sample(self.sampler);
         Amount::from_u128_with_scale(value, self.scale)
@@ -1,6 +1,7 @@
 use crate::common::amount::Amount;
 
 
+#[derive(Default)]
 pub struct Vector {
     pub data: Vec<Amount>,
 }
@@ -83,7 +84,7 @@ impl core::fmt::Display for Vector {
         let len = self.data.len();
 
         match n_cols_option {
-            Some(n_cols) if n_cols > 0 && len % n_cols == 0 => {
+            Some(n_cols) if n_cols > 0 && len.is_multiple_of(n_cols) => {
                 // --- MATRIX MODE ---
                 for (i, x) in self.data.iter().enumerate() {
                     write!(f, "{:0.max_scale_len$}", x, max_scale_len = max_scale_len)?;
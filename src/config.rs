@@ -0,0 +1,605 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use eyre::{Context, OptionExt, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chunking::ChunkingConfig, common::amount::Amount, hedging::HedgingConfig,
+    market::source::MarketDataConfig, retry::RetryConfig,
+};
+
+/// Declarative configuration of Vendor, its Indexes and runtime policies
+///
+/// Loaded from TOML or YAML file (by extension). Command-line flags take
+/// precedence over values from the file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub vendor: VendorConfig,
+    /// More Vendors run by the same process, each with its own signer
+    pub vendors: Vec<VendorConfig>,
+    pub indexes: Vec<IndexConfig>,
+    pub policy: PolicyConfig,
+    pub pulley: PulleyConfig,
+    pub tx: TxConfig,
+    pub http: HttpConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub rpc_url: String,
+    pub castle_address: Option<Address>,
+    pub custody_address: Option<Address>,
+    pub collateral_address: Option<Address>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VendorConfig {
+    pub vendor_id: u128,
+    /// Environment variable holding private key of the Vendor (`PRIVATE_KEY` when not set)
+    pub private_key_env: Option<String>,
+    /// Number of assets to generate when `assets` is empty
+    pub market_size: usize,
+    /// Asset universe of the Vendor
+    pub assets: Vec<u128>,
+    /// Margin per asset (random when empty)
+    pub margins: Vec<Amount>,
+    /// Maximum number of assets submitted in single transaction
+    pub chunk_size: usize,
+    /// How chunk size is derived from gas estimates of each call
+    pub chunking: ChunkingConfig,
+    /// Where prices, slopes and liquidity come from
+    pub market_data: MarketDataConfig,
+    /// How vendor inventory is hedged, and where
+    pub hedging: HedgingConfig,
+    /// File journaling vendor inventory and PnL (kept in memory only when not set)
+    pub ledger_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    pub index_id: u128,
+    /// Vendor serving this Index (defaults to `vendor.vendor_id`)
+    pub vendor_id: Option<u128>,
+    pub name: String,
+    /// Defaults to `X{index_size}`
+    pub symbol: Option<String>,
+    pub description: String,
+    pub methodology: String,
+    pub initial_price: Amount,
+    pub curator: Address,
+    pub custody: String,
+    /// Defaults to the Keeper wallet
+    pub operators: Vec<Address>,
+    /// Overrides `network.custody_address` for this Index
+    pub custody_address: Option<Address>,
+    /// Overrides `network.collateral_address` for this Index
+    pub collateral_address: Option<Address>,
+    pub max_order_size: Amount,
+    /// Number of assets to pick from Vendor when `assets` is empty
+    pub index_size: usize,
+    pub assets: Vec<u128>,
+    /// Weight per asset (random when empty)
+    pub weights: Vec<Amount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Keep processing order after claim only while more than this remains
+    pub min_remain: u128,
+    /// Interval of processing pending orders found on-chain (disabled when 0)
+    pub reconcile_interval_secs: u64,
+    /// How failed events are retried, per class of error
+    pub retry: RetryConfig,
+    /// File to append events that kept failing to (kept in memory only when not set)
+    pub dead_letter_file: Option<PathBuf>,
+    /// How many dead letters to keep in memory
+    pub dead_letter_capacity: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PulleyConfig {
+    /// Interval of polling log filter (provider default when not set)
+    pub poll_interval_ms: Option<u64>,
+    /// Delay before re-creating dropped subscription or filter
+    pub reconnect_delay_ms: u64,
+    /// How long to poll after subscription failed, before subscribing again
+    pub resubscribe_after_secs: u64,
+    /// File storing last processed block (kept in memory only when not set)
+    pub checkpoint_file: Option<PathBuf>,
+    /// Backfill logs starting from this block instead of checkpoint
+    pub from_block: Option<u64>,
+    /// Maximum number of blocks fetched in single backfill request
+    pub backfill_range: u64,
+    /// Number of blocks mined on top of the block with event before it is processed
+    pub confirmations: u64,
+    /// Interval of checking chain head, to release confirmed events and advance checkpoint
+    pub head_poll_interval_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Address of HTTP server exposing `/metrics` and control API (disabled when not set)
+    pub listen: Option<SocketAddr>,
+    /// Bearer token required by control API requests changing state (required unless listening on loopback)
+    pub auth_token: Option<String>,
+    /// Interval of polling gas balance of the wallet
+    pub balance_poll_interval_secs: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: "http://localhost:8547".into(),
+            castle_address: None,
+            custody_address: None,
+            collateral_address: None,
+        }
+    }
+}
+
+impl Default for VendorConfig {
+    fn default() -> Self {
+        Self {
+            vendor_id: 1,
+            private_key_env: None,
+            market_size: 5,
+            assets: Vec::new(),
+            margins: Vec::new(),
+            chunk_size: 500,
+            chunking: ChunkingConfig::default(),
+            market_data: MarketDataConfig::default(),
+            hedging: HedgingConfig::default(),
+            ledger_file: None,
+        }
+    }
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            index_id: 1001,
+            vendor_id: None,
+            name: "name".into(),
+            symbol: None,
+            description: "Description".into(),
+            methodology: "Methodology".into(),
+            initial_price: Amount::ZERO,
+            curator: Address::ZERO,
+            custody: "Custody".into(),
+            operators: Vec::new(),
+            custody_address: None,
+            collateral_address: None,
+            max_order_size: Amount::from_u128_with_scale(100, 0),
+            index_size: 3,
+            assets: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_remain: 100,
+            reconcile_interval_secs: 60,
+            retry: RetryConfig::default(),
+            dead_letter_file: None,
+            dead_letter_capacity: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TxConfig {
+    /// Maximum number of transactions awaiting receipts at once
+    pub max_in_flight: usize,
+    /// Cap of max fee per gas, in wei (no cap when not set)
+    pub max_fee_per_gas: Option<u128>,
+    /// Cap of max priority fee per gas, in wei (no cap when not set)
+    pub max_priority_fee_per_gas: Option<u128>,
+    /// How long to wait for receipt before replacing transaction
+    pub receipt_timeout_secs: u64,
+    /// How much to raise fees of replacement transaction (at least 10%)
+    pub fee_bump_percent: u64,
+    /// How many times to replace transaction before giving up
+    pub max_replacements: u32,
+    /// Only simulate state-changing calls with `eth_call`, and log values they return
+    pub dry_run: bool,
+    /// Gas balance each signer must have to start (not checked in dry run)
+    pub min_gas_balance: Amount,
+}
+
+impl Default for TxConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 16,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            receipt_timeout_secs: 60,
+            fee_bump_percent: 15,
+            max_replacements: 3,
+            dry_run: false,
+            min_gas_balance: Amount::from_u128_with_scale(1, 1),
+        }
+    }
+}
+
+impl TxConfig {
+    pub fn receipt_timeout(&self) -> Duration {
+        Duration::from_secs(self.receipt_timeout_secs.max(1))
+    }
+}
+
+impl Default for PulleyConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: None,
+            reconnect_delay_ms: 1000,
+            resubscribe_after_secs: 60,
+            checkpoint_file: None,
+            from_block: None,
+            backfill_range: 1000,
+            confirmations: 0,
+            head_poll_interval_ms: 1000,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            listen: None,
+            auth_token: None,
+            balance_poll_interval_secs: 30,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn balance_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.balance_poll_interval_secs.max(1))
+    }
+}
+
+impl PolicyConfig {
+    pub fn reconcile_interval(&self) -> Option<Duration> {
+        (0 < self.reconcile_interval_secs)
+            .then(|| Duration::from_secs(self.reconcile_interval_secs))
+    }
+}
+
+impl PulleyConfig {
+    pub fn poll_interval(&self) -> Option<Duration> {
+        self.poll_interval_ms.map(Duration::from_millis)
+    }
+
+    pub fn reconnect_delay(&self) -> Duration {
+        Duration::from_millis(self.reconnect_delay_ms)
+    }
+
+    pub fn resubscribe_after(&self) -> Duration {
+        Duration::from_secs(self.resubscribe_after_secs)
+    }
+
+    pub fn head_poll_interval(&self) -> Duration {
+        Duration::from_millis(self.head_poll_interval_ms.max(1))
+    }
+}
+
+impl NetworkConfig {
+    pub fn castle_address(&self) -> eyre::Result<Address> {
+        self.castle_address
+            .ok_or_eyre("Castle address is not configured")
+    }
+
+    pub fn custody_address(&self, index: &IndexConfig) -> eyre::Result<Address> {
+        index
+            .custody_address
+            .or(self.custody_address)
+            .ok_or_eyre("Custody address is not configured")
+    }
+
+    pub fn collateral_address(&self, index: &IndexConfig) -> eyre::Result<Address> {
+        index
+            .collateral_address
+            .or(self.collateral_address)
+            .ok_or_eyre("Collateral address is not configured")
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config: {}", path.display()))?;
+
+        let config: Config = match path.extension().and_then(|x| x.to_str()) {
+            Some("toml") => toml::from_str(&text).context("Failed to parse TOML config")?,
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&text).context("Failed to parse YAML config")?
+            }
+            _ => bail!("Unsupported config format: {}", path.display()),
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.pulley.backfill_range == 0 {
+            bail!("Backfill range must not be zero")
+        }
+
+        for (pos, vendor) in self.all_vendors().enumerate() {
+            if vendor.chunk_size == 0 {
+                bail!("Vendor {} chunk size must not be zero", vendor.vendor_id)
+            }
+
+            if !vendor.margins.is_empty() && vendor.margins.len() != vendor.assets.len() {
+                bail!(
+                    "Vendor {} has {} assets but {} margins",
+                    vendor.vendor_id,
+                    vendor.assets.len(),
+                    vendor.margins.len()
+                )
+            }
+
+            if self
+                .all_vendors()
+                .skip(pos + 1)
+                .any(|x| x.vendor_id == vendor.vendor_id)
+            {
+                bail!("Vendor {} is declared more than once", vendor.vendor_id)
+            }
+        }
+
+        for (pos, index) in self.indexes.iter().enumerate() {
+            if self.indexes[pos + 1..]
+                .iter()
+                .any(|x| x.index_id == index.index_id)
+            {
+                bail!("Index {} is declared more than once", index.index_id)
+            }
+
+            let Some(vendor) = self.vendor_config(self.index_vendor_id(index)) else {
+                bail!(
+                    "Index {} is served by undeclared Vendor {}",
+                    index.index_id,
+                    self.index_vendor_id(index)
+                )
+            };
+
+            if !index.weights.is_empty() && index.weights.len() != index.assets.len() {
+                bail!(
+                    "Index {} has {} assets but {} weights",
+                    index.index_id,
+                    index.assets.len(),
+                    index.weights.len()
+                )
+            }
+
+            if let Some(asset) = index
+                .assets
+                .iter()
+                .find(|a| !vendor.assets.is_empty() && !vendor.assets.contains(a))
+            {
+                bail!(
+                    "Index {} asset {} is not in Vendor assets",
+                    index.index_id,
+                    asset
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Configurations of all Vendors, starting with the one in `[vendor]` section
+    pub fn all_vendors(&self) -> impl Iterator<Item = &VendorConfig> {
+        std::iter::once(&self.vendor).chain(&self.vendors)
+    }
+
+    pub fn vendor_config(&self, vendor_id: u128) -> Option<&VendorConfig> {
+        self.all_vendors().find(|x| x.vendor_id == vendor_id)
+    }
+
+    /// Vendor serving given Index
+    pub fn index_vendor_id(&self, index: &IndexConfig) -> u128 {
+        index.vendor_id.unwrap_or(self.vendor.vendor_id)
+    }
+
+    /// Configuration of given Index, or defaults if not declared
+    pub fn index(&self, index_id: u128) -> IndexConfig {
+        self.indexes
+            .iter()
+            .find(|x| x.index_id == index_id)
+            .cloned()
+            .unwrap_or_else(|| IndexConfig {
+                index_id,
+                ..Default::default()
+            })
+    }
+
+    /// Mutable configuration of given Index, declaring it with defaults if missing
+    pub fn index_entry(&mut self, index_id: u128) -> &mut IndexConfig {
+        let pos = match self.indexes.iter().position(|x| x.index_id == index_id) {
+            Some(pos) => pos,
+            None => {
+                self.indexes.push(IndexConfig {
+                    index_id,
+                    ..Default::default()
+                });
+                self.indexes.len() - 1
+            }
+        };
+        &mut self.indexes[pos]
+    }
+
+    /// Index to use when none was given on command-line
+    pub fn default_index_id(&self) -> u128 {
+        self.indexes
+            .first()
+            .map(|x| x.index_id)
+            .unwrap_or_else(|| IndexConfig::default().index_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::venue::execution::VenueConfig;
+
+    #[test]
+    fn test_config_toml() {
+        let config: Config = toml::from_str(
+            r#"
+            [network]
+            castle_address = "0x0000000000000000000000000000000000000001"
+
+            [vendor]
+            vendor_id = 101
+            assets = [1, 2, 3]
+            margins = ["0.1", "0.2", 1]
+
+            [[indexes]]
+            index_id = 1001
+            symbol = "ABC"
+            assets = [1, 3]
+            weights = ["0.5", "0.5"]
+            max_order_size = "250.5"
+
+            [policy]
+            min_remain = 1000
+            "#,
+        )
+        .unwrap();
+
+        config.validate().unwrap();
+
+        assert_eq!(config.network.rpc_url, "http://localhost:8547");
+        assert_eq!(config.vendor.vendor_id, 101);
+        assert_eq!(config.vendor.chunk_size, 500);
+        assert_eq!(config.vendor.margins[2], Amount::ONE);
+        assert_eq!(config.policy.min_remain, 1000);
+
+        let index = config.index(1001);
+        assert_eq!(index.symbol.as_deref(), Some("ABC"));
+        assert_eq!(index.description, "Description");
+        assert_eq!(index.max_order_size, "250.5".parse().unwrap());
+
+        let other = config.index(1002);
+        assert_eq!(other.index_id, 1002);
+        assert!(other.assets.is_empty());
+    }
+
+    #[test]
+    fn test_config_example() {
+        let config = Config::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/conveyor.example.toml"
+        ))
+        .unwrap();
+
+        assert_eq!(config.vendor.vendor_id, 101);
+        assert_eq!(config.index(1001).assets, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn test_config_validate() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+            vendor:
+              assets: [1, 2]
+            indexes:
+              - index_id: 1001
+                assets: [1, 5]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_vendors() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [vendor]
+            vendor_id = 101
+            assets = [1, 2]
+
+            [[vendors]]
+            vendor_id = 102
+            private_key_env = "VENDOR_102_KEY"
+            assets = [5, 6]
+
+            [[indexes]]
+            index_id = 1001
+            assets = [1, 2]
+
+            [[indexes]]
+            index_id = 1002
+            vendor_id = 102
+            assets = [5]
+            "#,
+        )
+        .unwrap();
+
+        config.validate().unwrap();
+        assert_eq!(config.index_vendor_id(&config.index(1001)), 101);
+        assert_eq!(config.index_vendor_id(&config.index(1002)), 102);
+        assert_eq!(
+            config
+                .vendor_config(102)
+                .unwrap()
+                .private_key_env
+                .as_deref(),
+            Some("VENDOR_102_KEY")
+        );
+
+        config.indexes[1].vendor_id = Some(103);
+        assert!(config.validate().is_err());
+
+        config.indexes[1].vendor_id = Some(102);
+        config.vendors[0].vendor_id = 101;
+        assert!(config.validate().is_err());
+
+        config.vendors[0].vendor_id = 102;
+        config.indexes[1].index_id = 1001;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_venue() {
+        let config: Config = toml::from_str(
+            r#"
+            [vendor.hedging.venue]
+            kind = "simulated"
+            latency_ms = 250
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            config.vendor.hedging.venue,
+            VenueConfig::Simulated {
+                latency_ms: 250,
+                max_liquidity_percent: 100
+            }
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use alloy::rpc::types::Log;
+
+/// What happened to the log pushed into [`Confirmations`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Pushed {
+    /// Log is waiting for confirmations
+    Pending,
+    /// Log removed by reorg was still pending, and has been dropped
+    Retracted,
+    /// Log removed by reorg was already released and forwarded
+    Orphaned { block_number: u64 },
+    /// Log removed by reorg was never forwarded, e.g. it was dropped or emitted before startup
+    Ignored,
+}
+
+/// Number of forwarded logs remembered, so that their removal by reorg is noticed
+const FORWARDED_CAPACITY: usize = 10_000;
+
+/// Holds logs until enough blocks are mined on top of the block containing them
+pub struct Confirmations {
+    depth: u64,
+    pending: BTreeMap<(u64, u64), Log>,
+    /// Block number and index of recently forwarded logs
+    forwarded: BTreeSet<(u64, u64)>,
+}
+
+fn log_key(log: &Log) -> (u64, u64) {
+    (
+        log.block_number.unwrap_or_default(),
+        log.log_index.unwrap_or_default(),
+    )
+}
+
+impl Confirmations {
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth,
+            pending: BTreeMap::new(),
+            forwarded: BTreeSet::new(),
+        }
+    }
+
+    pub fn depth(&self) -> u64 {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Highest block whose logs are confirmed at given head
+    pub fn safe_block(&self, head: u64) -> u64 {
+        head.saturating_sub(self.depth)
+    }
+
+    pub fn push(&mut self, log: Log) -> Pushed {
+        let key = log_key(&log);
+
+        if log.removed {
+            if self.pending.remove(&key).is_some() {
+                return Pushed::Retracted;
+            }
+            if self.forwarded.remove(&key) {
+                return Pushed::Orphaned {
+                    block_number: key.0,
+                };
+            }
+            return Pushed::Ignored;
+        }
+
+        // Same log may be delivered again, e.g. by backfill after reconnect
+        self.pending.insert(key, log);
+        Pushed::Pending
+    }
+
+    /// Take all logs confirmed at given head, in chain order
+    pub fn release(&mut self, head: u64) -> Vec<Log> {
+        let safe_block = self.safe_block(head);
+        let still_pending = self.pending.split_off(&(safe_block + 1, 0));
+        let confirmed = std::mem::replace(&mut self.pending, still_pending);
+        confirmed.into_values().collect()
+    }
+
+    /// Remember log released and forwarded, evicting the oldest ones
+    pub fn forwarded(&mut self, log: &Log) {
+        self.forwarded.insert(log_key(log));
+        while FORWARDED_CAPACITY < self.forwarded.len() {
+            self.forwarded.pop_first();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn log(block_number: u64, log_index: u64, removed: bool) -> Log {
+        Log {
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            removed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_confirmations() {
+        let mut confirmations = Confirmations::new(2);
+
+        assert_eq!(confirmations.push(log(10, 1, false)), Pushed::Pending);
+        assert_eq!(confirmations.push(log(10, 0, false)), Pushed::Pending);
+        assert_eq!(confirmations.push(log(11, 0, false)), Pushed::Pending);
+        assert_eq!(confirmations.push(log(11, 0, false)), Pushed::Pending);
+        assert_eq!(confirmations.len(), 3);
+
+        assert!(confirmations.release(11).is_empty());
+
+        let released = confirmations.release(12);
+        assert_eq!(released.len(), 2);
+        assert_eq!(released[0].log_index, Some(0));
+        assert_eq!(released[1].log_index, Some(1));
+        confirmations.forwarded(&released[0]);
+
+        assert_eq!(confirmations.push(log(11, 0, true)), Pushed::Retracted);
+        assert_eq!(
+            confirmations.push(log(10, 0, true)),
+            Pushed::Orphaned { block_number: 10 }
+        );
+        assert!(confirmations.is_empty());
+    }
+
+    #[test]
+    fn test_no_confirmations() {
+        let mut confirmations = Confirmations::new(0);
+
+        confirmations.push(log(10, 0, false));
+        assert_eq!(confirmations.release(10).len(), 1);
+        assert!(confirmations.is_empty());
+    }
+
+    #[test]
+    fn test_removed_never_forwarded() {
+        let mut confirmations = Confirmations::new(0);
+
+        // Log released, but not forwarded (e.g. not one of the events)
+        confirmations.push(log(10, 0, false));
+        assert_eq!(confirmations.release(10).len(), 1);
+        assert_eq!(confirmations.push(log(10, 0, true)), Pushed::Ignored);
+
+        // Log emitted before startup
+        assert_eq!(confirmations.push(log(5, 2, true)), Pushed::Ignored);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{fmt::Display, sync::Arc};
+
+use alloy::primitives::Address;
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use eyre::eyre;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    common::amount::Amount, config::Config, dead_letter::DeadLetter, order::Order,
+    pulley::ChainEvent,
+};
+
+/// Number of requests waiting for App loop, before HTTP handlers wait too
+const CONTROL_QUEUE_SIZE: usize = 16;
+
+/// Operation of Keeper or Vendor run on demand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    UpdateMarket,
+    UpdateQuote,
+    BuyOrder,
+    SellOrder,
+    UpdateSupply,
+}
+
+impl Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trigger::UpdateMarket => write!(f, "update_market"),
+            Trigger::UpdateQuote => write!(f, "update_quote"),
+            Trigger::BuyOrder => write!(f, "buy_order"),
+            Trigger::SellOrder => write!(f, "sell_order"),
+            Trigger::UpdateSupply => write!(f, "update_supply"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub index_id: u128,
+    pub vendor_id: u128,
+    pub vault_address: Address,
+    pub custody_address: Address,
+    pub collateral_address: Address,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VendorStatus {
+    pub vendor_id: u128,
+    pub vendor_address: Address,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub paused: bool,
+    pub keeper_address: Address,
+    pub vendors: Vec<VendorStatus>,
+    pub indexes: Vec<IndexStatus>,
+    pub dead_letters: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetWeight {
+    pub asset: u128,
+    pub weight: Amount,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexAssets {
+    pub index_id: u128,
+    pub assets: Vec<AssetWeight>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingOrder {
+    pub index_id: u128,
+    pub trader: Address,
+    pub order: Order,
+}
+
+/// Event received by App, most recent last
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentEvent {
+    pub block_number: Option<u64>,
+    pub tx_hash: Option<String>,
+    pub log_index: Option<u64>,
+    pub message: String,
+    /// Time it took to process, including retries
+    pub elapsed_ms: u128,
+}
+
+impl RecentEvent {
+    pub fn new(event: &ChainEvent, elapsed_ms: u128) -> Self {
+        Self {
+            block_number: event.meta.block_number,
+            tx_hash: event.meta.tx_hash.map(|x| x.to_string()),
+            log_index: event.meta.log_index,
+            message: format!("{:?}", event.message),
+            elapsed_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventsReport {
+    pub recent: Vec<RecentEvent>,
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+/// Request handled by App loop in between events
+pub enum ControlRequest {
+    Status(oneshot::Sender<StatusReport>),
+    Index(oneshot::Sender<Vec<IndexAssets>>),
+    Events(oneshot::Sender<EventsReport>),
+    PendingOrders(oneshot::Sender<eyre::Result<Vec<PendingOrder>>>),
+    /// Run operation for given Index, or for all of them
+    Trigger(Trigger, Option<u128>, oneshot::Sender<eyre::Result<()>>),
+    /// Stop or start processing events, replying with previous state
+    SetPaused(bool, oneshot::Sender<bool>),
+}
+
+/// Sends requests from HTTP handlers to App loop
+#[derive(Clone)]
+pub struct ControlHandle {
+    config: Arc<Config>,
+    auth_token: Option<String>,
+    /// Server is reachable from this host only, so requests may go without token
+    loopback: bool,
+    sender: mpsc::Sender<ControlRequest>,
+}
+
+impl ControlHandle {
+    pub fn new(mut config: Config) -> (Self, mpsc::Receiver<ControlRequest>) {
+        let (sender, receiver) = mpsc::channel(CONTROL_QUEUE_SIZE);
+        let auth_token = config.http.auth_token.take();
+        let loopback = config.http.listen.is_some_and(|x| x.ip().is_loopback());
+        if auth_token.is_some() {
+            // Config is served as is, so token must not be part of it
+            config.http.auth_token = Some("<redacted>".into());
+        }
+        // RPC URL often carries API key of the node provider
+        config.network.rpc_url = "<redacted>".into();
+        let handle = Self {
+            config: Arc::new(config),
+            auth_token,
+            loopback,
+            sender,
+        };
+        (handle, receiver)
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Send request to App loop, and wait until it replies
+    pub async fn request<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<T>) -> ControlRequest,
+    ) -> eyre::Result<T> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(request(reply))
+            .await
+            .map_err(|_| eyre!("App loop is not running"))?;
+        response
+            .await
+            .map_err(|_| eyre!("App loop dropped the request"))
+    }
+
+    /// Check bearer token of the request changing state
+    ///
+    /// Without configured token, only server listening on loopback address accepts such requests.
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), ApiError> {
+        let Some(auth_token) = &self.auth_token else {
+            if self.loopback {
+                return Ok(());
+            }
+            return Err(ApiError(
+                StatusCode::FORBIDDEN,
+                eyre!("Control API on non-loopback address requires auth_token in [http] section"),
+            ));
+        };
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer != Some(auth_token.as_str()) {
+            return Err(ApiError(
+                StatusCode::UNAUTHORIZED,
+                eyre!("Missing or invalid bearer token"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct ApiError(StatusCode, eyre::Report);
+
+impl From<eyre::Report> for ApiError {
+    fn from(err: eyre::Report) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": format!("{:#}", self.1) });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// Query of trigger request (query strings can't carry u128, so it is parsed here)
+#[derive(Debug, Deserialize)]
+struct TriggerQuery {
+    index_id: Option<String>,
+}
+
+impl TriggerQuery {
+    fn index_id(&self) -> Result<Option<u128>, ApiError> {
+        self.index_id
+            .as_deref()
+            .map(|x| x.parse::<u128>())
+            .transpose()
+            .map_err(|err| ApiError(StatusCode::BAD_REQUEST, eyre!("Invalid index_id: {}", err)))
+    }
+}
+
+async fn get_config(State(handle): State<ControlHandle>) -> Json<Config> {
+    Json(handle.get_config().clone())
+}
+
+async fn get_status(State(handle): State<ControlHandle>) -> ApiResult<StatusReport> {
+    Ok(Json(handle.request(ControlRequest::Status).await?))
+}
+
+async fn get_index(State(handle): State<ControlHandle>) -> ApiResult<Vec<IndexAssets>> {
+    Ok(Json(handle.request(ControlRequest::Index).await?))
+}
+
+async fn get_events(State(handle): State<ControlHandle>) -> ApiResult<EventsReport> {
+    Ok(Json(handle.request(ControlRequest::Events).await?))
+}
+
+async fn get_orders(State(handle): State<ControlHandle>) -> ApiResult<Vec<PendingOrder>> {
+    Ok(Json(handle.request(ControlRequest::PendingOrders).await??))
+}
+
+async fn post_trigger(
+    State(handle): State<ControlHandle>,
+    headers: HeaderMap,
+    Path(trigger): Path<Trigger>,
+    Query(query): Query<TriggerQuery>,
+) -> ApiResult<serde_json::Value> {
+    handle.authorize(&headers)?;
+    let index_id = query.index_id()?;
+    handle
+        .request(|reply| ControlRequest::Trigger(trigger, index_id, reply))
+        .await??;
+    Ok(Json(
+        serde_json::json!({ "trigger": trigger, "index_id": index_id }),
+    ))
+}
+
+async fn set_paused(
+    handle: ControlHandle,
+    headers: HeaderMap,
+    paused: bool,
+) -> ApiResult<serde_json::Value> {
+    handle.authorize(&headers)?;
+    let was_paused = handle
+        .request(|reply| ControlRequest::SetPaused(paused, reply))
+        .await?;
+    Ok(Json(
+        serde_json::json!({ "paused": paused, "was_paused": was_paused }),
+    ))
+}
+
+async fn post_pause(
+    State(handle): State<ControlHandle>,
+    headers: HeaderMap,
+) -> ApiResult<serde_json::Value> {
+    set_paused(handle, headers, true).await
+}
+
+async fn post_resume(
+    State(handle): State<ControlHandle>,
+    headers: HeaderMap,
+) -> ApiResult<serde_json::Value> {
+    set_paused(handle, headers, false).await
+}
+
+/// Routes of control API, to be merged into HTTP server
+pub fn router(handle: ControlHandle) -> Router {
+    Router::new()
+        .route("/config", get(get_config))
+        .route("/status", get(get_status))
+        .route("/index", get(get_index))
+        .route("/events", get(get_events))
+        .route("/orders", get(get_orders))
+        .route("/trigger/{trigger}", post(post_trigger))
+        .route("/pause", post(post_pause))
+        .route("/resume", post(post_resume))
+        .with_state(handle)
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body::Body, http::HeaderValue, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_control_handle() {
+        let mut config = Config::default();
+        config.http.auth_token = Some("secret".into());
+        let (handle, mut receiver) = ControlHandle::new(config);
+        assert_eq!(handle.get_config().network.rpc_url, "<redacted>");
+
+        tokio::spawn(async move {
+            let mut paused = false;
+            while let Some(request) = receiver.recv().await {
+                if let ControlRequest::SetPaused(value, reply) = request {
+                    let _ = reply.send(std::mem::replace(&mut paused, value));
+                }
+            }
+        });
+
+        let mut headers = HeaderMap::new();
+        assert!(handle.authorize(&headers).is_err());
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        assert!(handle.authorize(&headers).is_ok());
+
+        let pause = |paused| handle.request(move |reply| ControlRequest::SetPaused(paused, reply));
+        assert!(!pause(true).await.unwrap());
+        assert!(pause(false).await.unwrap());
+
+        let trigger: Trigger = serde_json::from_str(r#""update_supply""#).unwrap();
+        assert_eq!(trigger, Trigger::UpdateSupply);
+
+        let uri = "/trigger/update_quote?index_id=1001".parse().unwrap();
+        let Query(query) = Query::<TriggerQuery>::try_from_uri(&uri).unwrap();
+        assert_eq!(query.index_id().ok(), Some(Some(1001)));
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_post_rejected() {
+        let mut config = Config::default();
+        config.http.listen = Some("0.0.0.0:9100".parse().unwrap());
+        let (handle, _receiver) = ControlHandle::new(config);
+
+        let request = Request::post("/pause").body(Body::empty()).unwrap();
+        let response = router(handle.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut config = Config::default();
+        config.http.listen = Some("127.0.0.1:9100".parse().unwrap());
+        let (handle, _receiver) = ControlHandle::new(config);
+        assert!(handle.authorize(&HeaderMap::new()).is_ok());
+    }
+}
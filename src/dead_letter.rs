@@ -0,0 +1,117 @@
+use std::{collections::VecDeque, io::Write, path::PathBuf};
+
+use eyre::Context;
+use serde::Serialize;
+use tracing::error;
+
+use crate::{pulley::ChainEvent, retry::ErrorClass};
+
+/// Event that could not be processed even after retries
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub block_number: Option<u64>,
+    pub tx_hash: Option<String>,
+    pub log_index: Option<u64>,
+    pub message: String,
+    pub class: ErrorClass,
+    pub attempts: u32,
+    pub error: String,
+}
+
+impl DeadLetter {
+    pub fn new(event: &ChainEvent, class: ErrorClass, attempts: u32, err: &eyre::Report) -> Self {
+        Self {
+            block_number: event.meta.block_number,
+            tx_hash: event.meta.tx_hash.map(|x| x.to_string()),
+            log_index: event.meta.log_index,
+            message: format!("{:?}", event.message),
+            class,
+            attempts,
+            error: format!("{:#}", err),
+        }
+    }
+}
+
+/// Keeps most recent dead letters, and appends all of them to JSONL file (when configured)
+pub struct DeadLetterQueue {
+    path: Option<PathBuf>,
+    capacity: usize,
+    letters: VecDeque<DeadLetter>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(path: Option<PathBuf>, capacity: usize) -> Self {
+        Self {
+            path,
+            capacity,
+            letters: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    pub fn letters(&self) -> impl Iterator<Item = &DeadLetter> {
+        self.letters.iter()
+    }
+
+    /// Keep the letter, and append it to the file
+    ///
+    /// Failing to write the file must not stop processing of later events,
+    /// so the error is only logged.
+    pub fn push(&mut self, letter: DeadLetter) {
+        if let Err(err) = self.append(&letter) {
+            error!("Failed to persist dead letter: {:?}", err);
+        }
+
+        self.letters.push_back(letter);
+        while self.capacity < self.letters.len() {
+            self.letters.pop_front();
+        }
+    }
+
+    fn append(&self, letter: &DeadLetter) -> eyre::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open dead letters: {}", path.display()))?;
+        let line = serde_json::to_string(letter)?;
+        writeln!(file, "{}", line)
+            .with_context(|| format!("Failed to write dead letters: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pulley::{ChainMessage, EventMeta};
+
+    use super::*;
+
+    #[test]
+    fn test_dead_letter_unwritable_file() {
+        let path = std::env::temp_dir().join("conveyor-missing-dir/dead_letters.jsonl");
+        let mut queue = DeadLetterQueue::new(Some(path), 1);
+
+        let event = ChainEvent {
+            meta: EventMeta::default(),
+            message: ChainMessage::Reorg { block_number: 1 },
+        };
+        let err = eyre::eyre!("reverted");
+        queue.push(DeadLetter::new(&event, ErrorClass::Revert, 3, &err));
+        queue.push(DeadLetter::new(&event, ErrorClass::Revert, 5, &err));
+
+        // Kept in memory even though file could not be written
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.letters().next().map(|x| x.attempts), Some(5));
+    }
+}
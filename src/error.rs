@@ -0,0 +1,286 @@
+use std::fmt::Display;
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    providers::PendingTransactionError,
+    sol_types::{Panic, Revert, SolError, SolInterface},
+    transports::{RpcError, TransportError},
+};
+
+use crate::interfaces::treasury::ITreasury::{self, ITreasuryErrors};
+
+/// Reason of a contract revert, decoded from revert data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConveyorError {
+    OwnableUnauthorizedAccount(Address),
+    OwnableInvalidOwner(Address),
+    Erc20InsufficientBalance {
+        sender: Address,
+        balance: U256,
+        needed: U256,
+    },
+    Erc20InsufficientAllowance {
+        spender: Address,
+        allowance: U256,
+        needed: U256,
+    },
+    Erc20InvalidSender(Address),
+    Erc20InvalidReceiver(Address),
+    Erc20InvalidSpender(Address),
+    Erc20InvalidApprover(Address),
+    AddressEmptyCode(Address),
+    FailedCall,
+    /// Call made by the contract failed, with reason decoded when possible
+    FailedCallWithReason(Box<ConveyorError>),
+    InvalidVersion(u32),
+    /// Other error declared by contract interfaces, e.g. upgrade errors
+    Declared(&'static str),
+    /// Standard `Error(string)`
+    Revert(String),
+    /// Standard `Panic(uint256)`
+    Panic(U256),
+    /// Revert data not matching any known error (empty when reverted without reason)
+    Unknown(Bytes),
+}
+
+impl ConveyorError {
+    /// Decode revert data returned by failed call
+    pub fn decode(data: &[u8]) -> Self {
+        if let Ok(err) = ITreasuryErrors::abi_decode(data) {
+            return Self::from(err);
+        }
+        if let Ok(revert) = Revert::abi_decode(data) {
+            return Self::Revert(revert.reason);
+        }
+        if let Ok(panic) = Panic::abi_decode(data) {
+            return Self::Panic(panic.code);
+        }
+        Self::Unknown(Bytes::copy_from_slice(data))
+    }
+
+    /// Find revert data in the chain of causes of the error, and decode it
+    pub fn from_report(err: &eyre::Report) -> Option<Self> {
+        err.chain().find_map(|cause| {
+            if let Some(err) = cause.downcast_ref::<ConveyorError>() {
+                return Some(err.clone());
+            }
+            if let Some(err) = cause.downcast_ref::<alloy::contract::Error>() {
+                return match err {
+                    alloy::contract::Error::TransportError(err) => revert_data(err),
+                    alloy::contract::Error::PendingTransactionError(
+                        PendingTransactionError::TransportError(err),
+                    ) => revert_data(err),
+                    _ => None,
+                }
+                .map(|data| Self::decode(&data));
+            }
+            if let Some(err) = cause.downcast_ref::<TransportError>() {
+                return revert_data(err).map(|data| Self::decode(&data));
+            }
+            None
+        })
+    }
+
+    /// Replace error with decoded revert reason (keeping its message as context)
+    pub fn decode_report(err: eyre::Report) -> eyre::Report {
+        match Self::from_report(&err) {
+            Some(decoded) if err.downcast_ref::<ConveyorError>().is_none() => {
+                eyre::Report::new(decoded).wrap_err(format!("{:#}", err))
+            }
+            _ => err,
+        }
+    }
+}
+
+fn revert_data(err: &TransportError) -> Option<Bytes> {
+    match err {
+        RpcError::ErrorResp(payload) => payload.as_revert_data(),
+        _ => None,
+    }
+}
+
+impl From<ITreasuryErrors> for ConveyorError {
+    fn from(err: ITreasuryErrors) -> Self {
+        match err {
+            ITreasuryErrors::OwnableUnauthorizedAccount(ITreasury::OwnableUnauthorizedAccount(
+                x,
+            )) => Self::OwnableUnauthorizedAccount(x),
+            ITreasuryErrors::OwnableInvalidOwner(ITreasury::OwnableInvalidOwner(x)) => {
+                Self::OwnableInvalidOwner(x)
+            }
+            ITreasuryErrors::ERC20InsufficientBalance(ITreasury::ERC20InsufficientBalance {
+                _0: sender,
+                _1: balance,
+                _2: needed,
+            }) => Self::Erc20InsufficientBalance {
+                sender,
+                balance,
+                needed,
+            },
+            ITreasuryErrors::ERC20InsufficientAllowance(
+                ITreasury::ERC20InsufficientAllowance {
+                    _0: spender,
+                    _1: allowance,
+                    _2: needed,
+                },
+            ) => Self::Erc20InsufficientAllowance {
+                spender,
+                allowance,
+                needed,
+            },
+            ITreasuryErrors::ERC20InvalidSender(ITreasury::ERC20InvalidSender(x)) => {
+                Self::Erc20InvalidSender(x)
+            }
+            ITreasuryErrors::ERC20InvalidReceiver(ITreasury::ERC20InvalidReceiver(x)) => {
+                Self::Erc20InvalidReceiver(x)
+            }
+            ITreasuryErrors::ERC20InvalidSpender(ITreasury::ERC20InvalidSpender(x)) => {
+                Self::Erc20InvalidSpender(x)
+            }
+            ITreasuryErrors::ERC20InvalidApprover(ITreasury::ERC20InvalidApprover(x)) => {
+                Self::Erc20InvalidApprover(x)
+            }
+            ITreasuryErrors::AddressEmptyCode(ITreasury::AddressEmptyCode(x)) => {
+                Self::AddressEmptyCode(x)
+            }
+            ITreasuryErrors::FailedCall(_) => Self::FailedCall,
+            ITreasuryErrors::FailedCallWithReason(ITreasury::FailedCallWithReason(x)) => {
+                Self::FailedCallWithReason(Box::new(Self::decode(&x)))
+            }
+            ITreasuryErrors::InvalidVersion(ITreasury::InvalidVersion(x)) => {
+                Self::InvalidVersion(x)
+            }
+            ITreasuryErrors::UUPSUnauthorizedCallContext(_) => {
+                Self::Declared(ITreasury::UUPSUnauthorizedCallContext::SIGNATURE)
+            }
+            ITreasuryErrors::UUPSUnsupportedProxiableUUID(_) => {
+                Self::Declared(ITreasury::UUPSUnsupportedProxiableUUID::SIGNATURE)
+            }
+            ITreasuryErrors::ERC1967InvalidImplementation(_) => {
+                Self::Declared(ITreasury::ERC1967InvalidImplementation::SIGNATURE)
+            }
+            ITreasuryErrors::ERC1967InvalidAdmin(_) => {
+                Self::Declared(ITreasury::ERC1967InvalidAdmin::SIGNATURE)
+            }
+            ITreasuryErrors::ERC1967InvalidBeacon(_) => {
+                Self::Declared(ITreasury::ERC1967InvalidBeacon::SIGNATURE)
+            }
+            ITreasuryErrors::ERC1967NonPayable(_) => {
+                Self::Declared(ITreasury::ERC1967NonPayable::SIGNATURE)
+            }
+            ITreasuryErrors::InvalidInitialization(_) => {
+                Self::Declared(ITreasury::InvalidInitialization::SIGNATURE)
+            }
+        }
+    }
+}
+
+impl Display for ConveyorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OwnableUnauthorizedAccount(account) => {
+                write!(f, "reverted: account {} is not authorized", account)
+            }
+            Self::OwnableInvalidOwner(owner) => write!(f, "reverted: invalid owner {}", owner),
+            Self::Erc20InsufficientBalance {
+                sender,
+                balance,
+                needed,
+            } => write!(
+                f,
+                "reverted: insufficient balance of {} ({} < {})",
+                sender, balance, needed
+            ),
+            Self::Erc20InsufficientAllowance {
+                spender,
+                allowance,
+                needed,
+            } => write!(
+                f,
+                "reverted: insufficient allowance of {} ({} < {})",
+                spender, allowance, needed
+            ),
+            Self::Erc20InvalidSender(x) => write!(f, "reverted: invalid sender {}", x),
+            Self::Erc20InvalidReceiver(x) => write!(f, "reverted: invalid receiver {}", x),
+            Self::Erc20InvalidSpender(x) => write!(f, "reverted: invalid spender {}", x),
+            Self::Erc20InvalidApprover(x) => write!(f, "reverted: invalid approver {}", x),
+            Self::AddressEmptyCode(x) => write!(f, "reverted: no code at {}", x),
+            Self::FailedCall => write!(f, "reverted: failed call"),
+            Self::FailedCallWithReason(reason) => write!(f, "failed call {}", reason),
+            Self::InvalidVersion(x) => write!(f, "reverted: invalid version {}", x),
+            Self::Declared(signature) => write!(f, "reverted: {}", signature),
+            Self::Revert(reason) => write!(f, "reverted: {:?}", reason),
+            Self::Panic(code) => match Panic::from(*code).kind() {
+                Some(kind) => write!(f, "reverted: panic {} ({})", code, kind),
+                None => write!(f, "reverted: panic {}", code),
+            },
+            Self::Unknown(data) if data.is_empty() => write!(f, "reverted without reason"),
+            Self::Unknown(data) => write!(f, "unknown revert {}", data),
+        }
+    }
+}
+
+impl std::error::Error for ConveyorError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let trader = Address::repeat_byte(7);
+
+        let data = ITreasury::ERC20InsufficientAllowance {
+            _0: trader,
+            _1: U256::from(1),
+            _2: U256::from(2),
+        }
+        .abi_encode();
+        assert_eq!(
+            ConveyorError::decode(&data),
+            ConveyorError::Erc20InsufficientAllowance {
+                spender: trader,
+                allowance: U256::from(1),
+                needed: U256::from(2),
+            }
+        );
+
+        let reason = Revert::from("Not enough liquidity").abi_encode();
+        let data = ITreasury::FailedCallWithReason(reason.into()).abi_encode();
+        assert_eq!(
+            ConveyorError::decode(&data),
+            ConveyorError::FailedCallWithReason(Box::new(ConveyorError::Revert(
+                "Not enough liquidity".to_owned()
+            )))
+        );
+
+        let data = Panic::from(U256::from(0x11)).abi_encode();
+        assert_eq!(
+            ConveyorError::decode(&data),
+            ConveyorError::Panic(U256::from(0x11))
+        );
+        assert!(
+            ConveyorError::decode(&data)
+                .to_string()
+                .contains("overflow")
+        );
+
+        let data = [0xde, 0xad, 0xbe, 0xef, 0x00];
+        assert!(matches!(
+            ConveyorError::decode(&data),
+            ConveyorError::Unknown(_)
+        ));
+
+        // Custom error of unknown selector, even when its bytes happen to be text
+        let data = b"Oops";
+        assert_eq!(
+            ConveyorError::decode(data).to_string(),
+            "unknown revert 0x4f6f7073"
+        );
+
+        assert_eq!(
+            ConveyorError::decode(&[]).to_string(),
+            "reverted without reason"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::amount::Amount,
+    inventory::{AssetQuantities, LongShort},
+    venue::execution::{Fill, HedgeOrder, Side, VenueConfig},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HedgingConfig {
+    /// Where hedge orders are executed
+    pub venue: VenueConfig,
+    /// Deltas smaller than this are not hedged
+    pub min_quantity: Amount,
+    /// Interval of polling venue for fills, while orders are in-flight
+    pub poll_interval_ms: u64,
+}
+
+impl Default for HedgingConfig {
+    fn default() -> Self {
+        Self {
+            venue: VenueConfig::default(),
+            min_quantity: Amount::ZERO,
+            poll_interval_ms: 1000,
+        }
+    }
+}
+
+impl HedgingConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.max(1))
+    }
+}
+
+fn to_signed(amount: Amount) -> i128 {
+    i128::try_from(amount.to_u128_raw()).unwrap_or(i128::MAX)
+}
+
+/// Net position, i.e. long minus short quantity
+fn net(quantity: &LongShort) -> i128 {
+    to_signed(quantity.long) - to_signed(quantity.short)
+}
+
+fn from_net(net: i128) -> LongShort {
+    let amount = Amount::from_u128_raw(net.unsigned_abs());
+    if net < 0 {
+        LongShort {
+            long: Amount::ZERO,
+            short: amount,
+        }
+    } else {
+        LongShort {
+            long: amount,
+            short: Amount::ZERO,
+        }
+    }
+}
+
+/// Inventory of the vendor built from fills, and hedge orders still in-flight
+///
+/// Supply is what vendor actually holds, i.e. sum of all fills reported by the
+/// venue. Delta read from chain is relative to supply last submitted, so it is
+/// corrected by fills not yet submitted and by orders not yet filled, before
+/// deciding what to hedge next.
+pub struct Hedger {
+    min_quantity: Amount,
+    poll_interval: Duration,
+    loaded: bool,
+    /// Net position of each asset, from all fills
+    positions: BTreeMap<u128, i128>,
+    /// Net position of each asset, as last submitted on-chain
+    submitted: BTreeMap<u128, i128>,
+    /// Asset and signed quantity of orders not yet filled
+    in_flight: HashMap<u64, (u128, i128)>,
+    next_order_id: u64,
+}
+
+impl Hedger {
+    pub fn new(config: &HedgingConfig) -> Self {
+        Self {
+            min_quantity: config.min_quantity,
+            poll_interval: config.poll_interval(),
+            loaded: false,
+            positions: BTreeMap::new(),
+            submitted: BTreeMap::new(),
+            in_flight: HashMap::new(),
+            next_order_id: 1,
+        }
+    }
+
+    pub fn get_poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Whether inventory was loaded from supply on-chain
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Start from supply already on-chain, e.g. after restart
+    pub fn load_supply(&mut self, supply: &AssetQuantities) {
+        self.positions = supply
+            .iter()
+            .map(|(asset, quantity)| (*asset, net(quantity)))
+            .collect();
+        self.submitted = self.positions.clone();
+        self.loaded = true;
+    }
+
+    pub fn has_in_flight(&self) -> bool {
+        !self.in_flight.is_empty()
+    }
+
+    pub fn get_position(&self, asset: u128) -> LongShort {
+        from_net(self.positions.get(&asset).copied().unwrap_or_default())
+    }
+
+    /// Orders bringing delta of each asset back to zero
+    pub fn plan(&mut self, delta: &AssetQuantities) -> Vec<HedgeOrder> {
+        let min_quantity = to_signed(self.min_quantity);
+        let mut orders = Vec::new();
+
+        for (asset, quantity) in delta.iter() {
+            let unsubmitted = self.positions.get(asset).copied().unwrap_or_default()
+                - self.submitted.get(asset).copied().unwrap_or_default();
+            let in_flight: i128 = self
+                .in_flight
+                .values()
+                .filter(|(x, _)| x == asset)
+                .map(|(_, quantity)| quantity)
+                .sum();
+
+            let excess = net(quantity) + unsubmitted + in_flight;
+            if excess == 0 || excess.abs() < min_quantity {
+                continue;
+            }
+
+            let side = if excess < 0 { Side::Buy } else { Side::Sell };
+            let order_id = self.next_order_id;
+            self.next_order_id += 1;
+            self.in_flight.insert(order_id, (*asset, -excess));
+
+            orders.push(HedgeOrder {
+                order_id,
+                asset: *asset,
+                side,
+                quantity: Amount::from_u128_raw(excess.unsigned_abs()),
+            });
+        }
+
+        orders
+    }
+
+    /// Forget order, which venue didn't accept
+    pub fn cancel(&mut self, order_id: u64) {
+        self.in_flight.remove(&order_id);
+    }
+
+    pub fn apply_fill(&mut self, fill: &Fill) {
+        self.in_flight.remove(&fill.order_id);
+        let quantity = to_signed(fill.quantity);
+        let position = self.positions.entry(fill.asset).or_default();
+        match fill.side {
+            Side::Buy => *position += quantity,
+            Side::Sell => *position -= quantity,
+        }
+    }
+
+    /// Supply of assets, whose position changed since last submitted
+    pub fn pending_supply(&self) -> Vec<(u128, LongShort)> {
+        self.positions
+            .iter()
+            .filter(|(asset, position)| {
+                self.submitted.get(asset).copied().unwrap_or_default() != **position
+            })
+            .map(|(asset, position)| (*asset, from_net(*position)))
+            .collect()
+    }
+
+    pub fn mark_submitted(&mut self, supply: &[(u128, LongShort)]) {
+        for (asset, quantity) in supply {
+            self.submitted.insert(*asset, net(quantity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quantities(assets: &[(u128, i128)]) -> AssetQuantities {
+        AssetQuantities {
+            assets: assets
+                .iter()
+                .map(|(asset, net)| (*asset, from_net(*net)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_hedger() {
+        let mut hedger = Hedger::new(&HedgingConfig {
+            min_quantity: Amount::from_u128_raw(5),
+            ..Default::default()
+        });
+        hedger.load_supply(&quantities(&[(101, 0), (102, 10), (103, 0)]));
+
+        // Demand is 100 long of 101, 10 long of 102, and 3 short of 103
+        let delta = quantities(&[(101, -100), (102, 0), (103, 3)]);
+        let orders = hedger.plan(&delta);
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].side, Side::Buy);
+        assert_eq!(orders[0].quantity, Amount::from_u128_raw(100));
+
+        // Nothing new to hedge while order is in-flight
+        assert!(hedger.plan(&delta).is_empty());
+
+        hedger.apply_fill(&Fill {
+            order_id: orders[0].order_id,
+            asset: 101,
+            side: Side::Buy,
+            requested: Amount::from_u128_raw(100),
+            quantity: Amount::from_u128_raw(60),
+            price: Amount::ONE,
+        });
+        assert!(!hedger.has_in_flight());
+
+        // Delta on-chain is stale until supply is submitted
+        let orders = hedger.plan(&delta);
+        assert_eq!(orders[0].quantity, Amount::from_u128_raw(40));
+
+        let supply = hedger.pending_supply();
+        assert_eq!(supply, vec![(101, from_net(60))]);
+        hedger.mark_submitted(&supply);
+        assert!(hedger.pending_supply().is_empty());
+        assert_eq!(hedger.get_position(101).long, Amount::from_u128_raw(60));
+    }
+}
@@ -2,6 +2,7 @@ use alloy::sol;
 
 sol! {
     #[sol(rpc)]
+    #[allow(clippy::too_many_arguments)]
     interface IGuildmaster  {
         function submitIndex(uint128 vendor_id, uint128 index_id, string calldata name, string calldata symbol, string calldata description, string calldata methodology, uint128 initial_price, address curator, string calldata custody, address[] memory operators, address collateral_custody, address collateral_asset, uint128 max_order_size) external returns (address);
 
@@ -2,6 +2,7 @@ use alloy::sol;
 
 sol!{
     #[sol(rpc)]
+    #[allow(clippy::too_many_arguments)]
     interface IVault  {
         function initialize(address owner, address vault_implementation, address gate_to_castle) external;
 
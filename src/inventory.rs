@@ -0,0 +1,259 @@
+use std::fmt::Display;
+
+use alloy::primitives::Bytes;
+use eyre::{OptionExt, bail};
+
+use crate::common::{
+    amount::Amount,
+    constants::{
+        DELTA_LONG_OFFSET, DELTA_SHORT_OFFSET, DEMAND_LONG_OFFSET, DEMAND_SHORT_OFFSET,
+        SUPPLY_LONG_OFFSET, SUPPLY_SHORT_OFFSET,
+    },
+    labels::Labels,
+    vector::Vector,
+};
+
+/// Long and short quantities of single asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LongShort {
+    pub long: Amount,
+    pub short: Amount,
+}
+
+impl LongShort {
+    pub const ZERO: LongShort = LongShort {
+        long: Amount::ZERO,
+        short: Amount::ZERO,
+    };
+
+    pub fn is_zero(&self) -> bool {
+        self.long.is_zero() && self.short.is_zero()
+    }
+}
+
+impl Display for LongShort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{}/-{}", self.long, self.short)
+    }
+}
+
+/// Long and short quantities keyed by asset label, in order of vendor market assets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetQuantities {
+    pub assets: Vec<(u128, LongShort)>,
+}
+
+impl AssetQuantities {
+    /// Decode long and short vectors returned by Steward
+    ///
+    /// Both vectors must have one quantity per market asset. Vectors returned
+    /// empty (nothing was ever submitted) are decoded as zero quantities.
+    pub fn try_from_bytes(
+        market_assets: &Labels,
+        data: &[Bytes],
+        long_offset: usize,
+        short_offset: usize,
+    ) -> eyre::Result<Self> {
+        let decode = |offset: usize, side: &str| -> eyre::Result<Vector> {
+            let bytes = data
+                .get(offset)
+                .ok_or_eyre(format!("Missing {} vector", side))?;
+            if bytes.is_empty() {
+                return Ok(Vector {
+                    data: vec![Amount::ZERO; market_assets.data.len()],
+                });
+            }
+            let len = Vector::len_from_vec(bytes).ok_or_eyre(format!(
+                "Invalid {} vector length: {} bytes",
+                side,
+                bytes.len()
+            ))?;
+            if len != market_assets.data.len() {
+                bail!(
+                    "Invalid {} vector: expected {} assets, got {}",
+                    side,
+                    market_assets.data.len(),
+                    len
+                );
+            }
+            Ok(Vector::from_vec(bytes))
+        };
+
+        let long = decode(long_offset, "long")?;
+        let short = decode(short_offset, "short")?;
+
+        let assets = market_assets
+            .data
+            .iter()
+            .zip(long.data)
+            .zip(short.data)
+            .map(|((asset, long), short)| (*asset, LongShort { long, short }))
+            .collect();
+
+        Ok(Self { assets })
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn get(&self, asset: u128) -> Option<&LongShort> {
+        self.assets
+            .iter()
+            .find_map(|(label, quantity)| (*label == asset).then_some(quantity))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(u128, LongShort)> {
+        self.assets.iter()
+    }
+
+    /// Assets with non-zero long or short quantity
+    pub fn non_zero(&self) -> impl Iterator<Item = &(u128, LongShort)> {
+        self.assets
+            .iter()
+            .filter(|(_, quantity)| !quantity.is_zero())
+    }
+}
+
+impl Display for AssetQuantities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sepa = "";
+        for (asset, quantity) in &self.assets {
+            write!(f, "{}{}:{}", sepa, asset, quantity)?;
+            sepa = ",";
+        }
+        Ok(())
+    }
+}
+
+/// Demand of vendor assets, from `ISteward::getVendorDemand`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendorDemand {
+    pub vendor_id: u128,
+    pub quantities: AssetQuantities,
+}
+
+impl VendorDemand {
+    pub fn try_from_bytes(
+        vendor_id: u128,
+        market_assets: &Labels,
+        data: &[Bytes],
+    ) -> eyre::Result<Self> {
+        let quantities = AssetQuantities::try_from_bytes(
+            market_assets,
+            data,
+            DEMAND_LONG_OFFSET,
+            DEMAND_SHORT_OFFSET,
+        )
+        .map_err(|err| err.wrap_err(format!("Failed to decode demand of vendor {}", vendor_id)))?;
+        Ok(Self {
+            vendor_id,
+            quantities,
+        })
+    }
+}
+
+/// Supply of vendor assets, from `ISteward::getVendorSupply`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendorSupply {
+    pub vendor_id: u128,
+    pub quantities: AssetQuantities,
+}
+
+impl VendorSupply {
+    pub fn try_from_bytes(
+        vendor_id: u128,
+        market_assets: &Labels,
+        data: &[Bytes],
+    ) -> eyre::Result<Self> {
+        let quantities = AssetQuantities::try_from_bytes(
+            market_assets,
+            data,
+            SUPPLY_LONG_OFFSET,
+            SUPPLY_SHORT_OFFSET,
+        )
+        .map_err(|err| err.wrap_err(format!("Failed to decode supply of vendor {}", vendor_id)))?;
+        Ok(Self {
+            vendor_id,
+            quantities,
+        })
+    }
+}
+
+/// Difference between supply and demand of vendor assets, from `ISteward::getVendorDelta`
+///
+/// Long quantity is supply in excess of demand, and short quantity is demand
+/// not covered by supply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendorDelta {
+    pub vendor_id: u128,
+    pub quantities: AssetQuantities,
+}
+
+impl VendorDelta {
+    pub fn try_from_bytes(
+        vendor_id: u128,
+        market_assets: &Labels,
+        data: &[Bytes],
+    ) -> eyre::Result<Self> {
+        let quantities = AssetQuantities::try_from_bytes(
+            market_assets,
+            data,
+            DELTA_LONG_OFFSET,
+            DELTA_SHORT_OFFSET,
+        )
+        .map_err(|err| err.wrap_err(format!("Failed to decode delta of vendor {}", vendor_id)))?;
+        Ok(Self {
+            vendor_id,
+            quantities,
+        })
+    }
+}
+
+/// Demand, supply and delta of vendor assets read together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventorySnapshot {
+    pub demand: VendorDemand,
+    pub supply: VendorSupply,
+    pub delta: VendorDelta,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_asset_quantities() {
+        let market_assets = Labels::from_vec_u128(vec![101, 102, 103]);
+        let long = Vector::from_vec_u128(vec![10, 0, 30]);
+        let short = Vector::from_vec_u128(vec![0, 0, 5]);
+        let data = vec![Bytes::from(long.to_vec()), Bytes::from(short.to_vec())];
+
+        let demand = VendorDemand::try_from_bytes(1, &market_assets, &data).unwrap();
+        assert_eq!(demand.quantities.len(), 3);
+        assert_eq!(
+            demand.quantities.get(103),
+            Some(&LongShort {
+                long: Amount::from_u128_raw(30),
+                short: Amount::from_u128_raw(5),
+            })
+        );
+        assert_eq!(demand.quantities.non_zero().count(), 2);
+
+        let data = vec![Bytes::from(long.to_vec()), Bytes::new()];
+        let supply = VendorSupply::try_from_bytes(1, &market_assets, &data).unwrap();
+        assert_eq!(supply.quantities.get(101).unwrap().short, Amount::ZERO);
+
+        let short = Vector::from_vec_u128(vec![0, 5]);
+        let data = vec![Bytes::from(long.to_vec()), Bytes::from(short.to_vec())];
+        let err = VendorDelta::try_from_bytes(1, &market_assets, &data).unwrap_err();
+        assert!(format!("{:#}", err).contains("expected 3 assets, got 2"));
+
+        let err = VendorDelta::try_from_bytes(1, &market_assets, &data[..1]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Missing short vector"));
+    }
+}
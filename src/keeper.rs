@@ -1,27 +1,25 @@
+use std::collections::BTreeMap;
+
 use alloy::{
     primitives::Address,
     providers::{Provider, WalletProvider},
 };
-use eyre::{Context, bail};
+use eyre::{Context, OptionExt, bail, eyre};
 use itertools::Itertools;
-use tracing::{debug, info};
+use tracing::info;
 
 use crate::{
     common::{
-        amount::Amount,
-        constants::{
-            ORDER_BURNED_OFFSET, ORDER_COLLATERAL_OFFSET, ORDER_LOCKED_OFFSET, ORDER_MINTED_OFFSET,
-            ORDER_SPENT_OFFSET, ORDER_WITHDRAW_OFFSET,
-        },
-        labels::Labels,
-        rand_pick_assets::rand_pick_assets,
-        rand_value::ValueGen,
-        vector::Vector,
+        labels::Labels, rand_pick_assets::rand_pick_assets, rand_value::ValueGen, vector::Vector,
     },
+    config::IndexConfig,
+    error::ConveyorError,
     interfaces::{
         banker::IBanker, guildmaster::IGuildmaster, steward::ISteward,
         vault_native_orders::IVaultNativeOrders,
     },
+    order::{TotalOrder, TraderOrder, VendorOrder},
+    tx_manager::{TxManager, TxRequest},
 };
 
 pub struct Keeper<P>
@@ -29,6 +27,7 @@ where
     P: Provider + WalletProvider + Clone + 'static,
 {
     provider: P,
+    tx_manager: TxManager<P>,
     castle_address: Address,
     custody_address: Address,
     collateral_address: Address,
@@ -36,6 +35,7 @@ where
     index_id: u128,
     vendor_id: u128,
     assets: Labels,
+    weights: Vector,
 }
 
 impl<P> Keeper<P>
@@ -44,6 +44,7 @@ where
 {
     pub fn new(
         provider: P,
+        tx_manager: TxManager<P>,
         castle_address: Address,
         custody_address: Address,
         collateral_address: Address,
@@ -52,6 +53,7 @@ where
     ) -> Self {
         Self {
             provider,
+            tx_manager,
             castle_address,
             custody_address,
             collateral_address,
@@ -59,6 +61,7 @@ where
             vendor_id,
             vault_address: Address::ZERO,
             assets: Labels::new(),
+            weights: Vector::new(),
         }
     }
 
@@ -70,10 +73,19 @@ where
         self.vendor_id
     }
 
+    /// Address of the wallet sending transactions
+    pub fn get_address(&self) -> Address {
+        self.provider.default_signer_address()
+    }
+
     pub fn get_assets(&self) -> &Labels {
         &self.assets
     }
 
+    pub fn get_weights(&self) -> &Vector {
+        &self.weights
+    }
+
     pub fn get_custody_address(&self) -> Address {
         self.custody_address
     }
@@ -86,106 +98,151 @@ where
         self.vault_address
     }
 
-    pub async fn setup(&mut self, market_assets: &Labels, index_size: usize) -> eyre::Result<()> {
+    pub async fn setup(
+        &mut self,
+        market_assets: &Labels,
+        config: &IndexConfig,
+    ) -> eyre::Result<()> {
         info!("Handle: Keeper Setup");
         let guildmaster = IGuildmaster::new(self.castle_address, &self.provider);
         let keeper = self.provider.default_signer_address();
-        let max_order_size = Amount::from_u128_with_scale(100, 0);
+
+        let assets = if config.assets.is_empty() {
+            rand_pick_assets(market_assets, config.index_size)
+        } else {
+            Labels::from_vec_u128(config.assets.clone())
+        };
+
+        let asset_weights = if config.weights.is_empty() {
+            let mut weight_gen = ValueGen::new(1_00, 10_00, 2);
+            Vector {
+                data: assets.data.iter().map(|_| weight_gen.next()).collect_vec(),
+            }
+        } else {
+            Vector {
+                data: config.weights.clone(),
+            }
+        };
+
+        let operators = if config.operators.is_empty() {
+            vec![keeper]
+        } else {
+            config.operators.clone()
+        };
+
+        let symbol = config
+            .symbol
+            .clone()
+            .unwrap_or_else(|| format!("X{}", assets.data.len()));
 
         let submit_index_call = guildmaster.submitIndex(
             self.vendor_id,
             self.index_id,
-            "name".into(),
-            format!("X{}", index_size),
-            "Description".into(),
-            "Methodology".into(),
-            0,
-            Address::ZERO,
-            "Custody".into(),
-            vec![keeper],
+            config.name.clone(),
+            symbol,
+            config.description.clone(),
+            config.methodology.clone(),
+            config.initial_price.to_u128_raw(),
+            config.curator,
+            config.custody.clone(),
+            operators,
             self.custody_address,
             self.collateral_address,
-            max_order_size.to_u128_raw(),
+            config.max_order_size.to_u128_raw(),
         );
 
         info!("Calling submit Index...");
         let vault_address = submit_index_call
             .call()
             .await
+            .map_err(|err| ConveyorError::decode_report(err.into()))
             .context("Failed to call submit index")?;
 
         info!("Submitting Index...");
-        let submit_index = submit_index_call
-            .send()
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(submit_index_call))
             .await
             .context("Failed to submit index")?;
 
-        let submit_index_receipt = submit_index
-            .get_receipt()
-            .await
-            .context("Failed to submit index")?;
+        self.vault_address = vault_address;
 
-        if !submit_index_receipt.status() {
-            bail!("Failed to submit index: {:?}", submit_index_receipt)
-        }
+        info!("Submitting vote...");
+        let vote = guildmaster.submitVote(self.index_id, vec![].into());
 
-        debug!("Submit index receipt: {:?}", submit_index_receipt);
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(vote))
+            .await
+            .context("Failed to vote")?;
 
-        self.vault_address = vault_address;
+        info!("Submitting asset weights...");
+        let submit_asset_weights = guildmaster.submitAssetWeights(
+            self.index_id,
+            assets.to_vec().into(),
+            asset_weights.to_vec().into(),
+        );
 
-        info!("Submitting vote...");
-        let vote = guildmaster
-            .submitVote(self.index_id, vec![].into())
-            .send()
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(submit_asset_weights))
             .await
-            .context("Failed to send vote")?;
+            .context("Failed to submit asset weights")?;
 
-        let vote_receipt = vote.get_receipt().await.context("Failed to vote")?;
+        self.assets = assets;
+        self.weights = asset_weights;
 
-        if !vote_receipt.status() {
-            bail!("Failed to vote: {:?}", vote_receipt)
-        }
+        self.update_quote().await?;
 
-        debug!("Vote receipt: {:?}", vote_receipt);
+        Ok(())
+    }
 
-        let assets = rand_pick_assets(market_assets, index_size);
+    /// Rebuild Keeper state from an Index that was already submitted on-chain
+    pub async fn attach(&mut self) -> eyre::Result<()> {
+        info!("Handle: Keeper Attach");
+        let steward = ISteward::new(self.castle_address, &self.provider);
 
-        let mut weight_gen = ValueGen::new(1_00, 10_00, 2);
-        let asset_weights = Vector {
-            data: assets.data.iter().map(|_| weight_gen.next()).collect_vec(),
-        };
+        let vault_address = steward
+            .getVault(self.index_id)
+            .call()
+            .await
+            .context("Failed to obtain vault")?;
 
-        info!("Submitting asset weights...");
-        let submit_asset_weights = guildmaster
-            .submitAssetWeights(
-                self.index_id,
-                assets.to_vec().into(),
-                asset_weights.to_vec().into(),
-            )
-            .send()
+        if vault_address.is_zero() {
+            bail!("Index {} has no vault", self.index_id)
+        }
+
+        let assets_bytes = steward
+            .getIndexAssets(self.index_id)
+            .call()
             .await
-            .context("Failed to submit asset weights")?;
+            .context("Failed to obtain index assets")?;
 
-        let submit_asset_weights_receipt = submit_asset_weights
-            .get_receipt()
+        let weights_bytes = steward
+            .getIndexWeights(self.index_id)
+            .call()
             .await
-            .context("Failed to submit asset weights")?;
+            .context("Failed to obtain index weights")?;
 
-        if !submit_asset_weights_receipt.status() {
+        let assets = Labels::from_vec(assets_bytes);
+        let weights = Vector::from_vec(weights_bytes);
+
+        if assets.data.len() != weights.data.len() {
             bail!(
-                "Failed to submit asset weights: {:?}",
-                submit_asset_weights_receipt
+                "Index {} has {} assets but {} weights",
+                self.index_id,
+                assets.data.len(),
+                weights.data.len()
             )
         }
 
-        debug!(
-            "Submit asset weights receipt: {:?}",
-            submit_asset_weights_receipt
+        info!(
+            index_id = %self.index_id,
+            vault_address = %vault_address,
+            index_size = %assets.data.len(),
+            "Attached to Index"
         );
 
+        self.vault_address = vault_address;
         self.assets = assets;
-
-        self.update_quote().await?;
+        self.weights = weights;
 
         Ok(())
     }
@@ -195,22 +252,12 @@ where
         let banker = IBanker::new(self.castle_address, &self.provider);
 
         info!("Updating quote...");
-        let update_quote = banker
-            .updateIndexQuote(self.vendor_id, self.index_id)
-            .send()
-            .await
-            .context("Failed to update quote")?;
+        let update_quote = banker.updateIndexQuote(self.vendor_id, self.index_id);
 
-        let update_quote_receipt = update_quote
-            .get_receipt()
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(update_quote))
             .await
-            .context("Failed to confirm update quote")?;
-
-        if !update_quote_receipt.status() {
-            bail!("Failed to update quote: {:?}", update_quote_receipt)
-        }
-
-        debug!("Update quote receipt: {:?}", update_quote_receipt);
+            .context("Failed to update quote")?;
 
         Ok(())
     }
@@ -220,22 +267,12 @@ where
         let vault = IVaultNativeOrders::new(self.vault_address, &self.provider);
         let keeper = self.provider.default_signer_address();
 
-        let request = vault
-            .processPendingBuyOrder(keeper)
-            .send()
-            .await
-            .context("Failed to process pending Buy order")?;
+        let request = vault.processPendingBuyOrder(keeper);
 
-        let receipt = request
-            .get_receipt()
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(request))
             .await
-            .context("Failed to confirm process pending Buy order")?;
-
-        if !receipt.status() {
-            bail!("Failed to process pending Buy order: {:?}", receipt)
-        }
-
-        debug!("Update pending Buy order receipt: {:?}", receipt);
+            .context("Failed to process pending Buy order")?;
 
         Ok(())
     }
@@ -245,22 +282,12 @@ where
         let vault = IVaultNativeOrders::new(self.vault_address, &self.provider);
         let keeper = self.provider.default_signer_address();
 
-        let request = vault
-            .processPendingSellOrder(keeper)
-            .send()
-            .await
-            .context("Failed to process pending Sell order")?;
+        let request = vault.processPendingSellOrder(keeper);
 
-        let receipt = request
-            .get_receipt()
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(request))
             .await
-            .context("Failed to confirm process pending Sell order")?;
-
-        if !receipt.status() {
-            bail!("Failed to process pending Sell order: {:?}", receipt)
-        }
-
-        debug!("Update pending Sell order receipt: {:?}", receipt);
+            .context("Failed to process pending Sell order")?;
 
         Ok(())
     }
@@ -271,7 +298,7 @@ where
         Ok(())
     }
 
-    pub async fn log_trader_order(&self, trader: Address) -> eyre::Result<()> {
+    pub async fn get_trader_order(&self, trader: Address) -> eyre::Result<TraderOrder> {
         let steward = ISteward::new(self.castle_address, &self.provider);
 
         let order_bytes = steward
@@ -280,19 +307,217 @@ where
             .await
             .context("Failed to obtain trader order")?;
 
-        let order = Vector::from_vec(order_bytes);
+        TraderOrder::try_from_bytes(trader, order_bytes)
+    }
+
+    pub async fn log_trader_order(&self, trader: Address) -> eyre::Result<()> {
+        let TraderOrder { trader, order } = self.get_trader_order(trader).await?;
 
         info!(
             %trader,
-            collateral = %order.data[ORDER_COLLATERAL_OFFSET],
-            spent = %order.data[ORDER_SPENT_OFFSET],
-            minted = %order.data[ORDER_MINTED_OFFSET],
-            locked = %order.data[ORDER_LOCKED_OFFSET],
-            burned = %order.data[ORDER_BURNED_OFFSET],
-            withdraw = %order.data[ORDER_WITHDRAW_OFFSET],
+            collateral = %order.collateral,
+            spent = %order.spent,
+            minted = %order.minted,
+            locked = %order.locked,
+            burned = %order.burned,
+            withdraw = %order.withdraw,
             "💰 Trading Order"
         );
 
         Ok(())
     }
+
+    /// Orders of all traders of the Index, in the order Steward lists them
+    pub async fn get_trader_orders(&self) -> eyre::Result<Vec<TraderOrder>> {
+        let steward = ISteward::new(self.castle_address, &self.provider);
+
+        let trader_count = steward
+            .getTraderCount(self.index_id)
+            .call()
+            .await
+            .context("Failed to obtain trader count")?;
+
+        let mut orders = Vec::new();
+        for offset in 0..trader_count {
+            let trader = steward
+                .getTraderAt(self.index_id, offset)
+                .call()
+                .await
+                .context("Failed to obtain trader")?;
+
+            orders.push(self.get_trader_order(trader).await?);
+        }
+
+        Ok(orders)
+    }
+
+    pub async fn log_status(&self) -> eyre::Result<()> {
+        let steward = ISteward::new(self.castle_address, &self.provider);
+
+        let quote_bytes = steward
+            .getIndexQuote(self.index_id, self.vendor_id)
+            .call()
+            .await
+            .context("Failed to obtain index quote")?;
+
+        let vendor_order_bytes = steward
+            .getVendorOrder(self.index_id, self.vendor_id)
+            .call()
+            .await
+            .context("Failed to obtain vendor order")?;
+
+        let total_order_bytes = steward
+            .getTotalOrder(self.index_id)
+            .call()
+            .await
+            .context("Failed to obtain total order")?;
+
+        let trader_count = steward
+            .getTraderCount(self.index_id)
+            .call()
+            .await
+            .context("Failed to obtain trader count")?;
+
+        let quote = Vector::from_vec(quote_bytes);
+        let vendor_order = VendorOrder::try_from_bytes(self.vendor_id, vendor_order_bytes)?;
+        let total_order = TotalOrder::try_from_bytes(self.index_id, total_order_bytes)?;
+
+        info!(
+            index_id = %self.index_id,
+            vendor_id = %self.vendor_id,
+            vault_address = %self.vault_address,
+            assets = %self.assets,
+            weights = %self.weights,
+            "🏦 Index"
+        );
+
+        info!(
+            quote = %quote,
+            vendor_order = %vendor_order.order,
+            total_order = %total_order.order,
+            %trader_count,
+            "📋 Status"
+        );
+
+        Ok(())
+    }
+}
+
+/// Keepers of all Indexes served by this process, keyed by Index id
+///
+/// Each Index has its own Vault and assets, while quotes of several Indexes
+/// of the same Vendor are updated in single transaction.
+pub struct Keepers<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    provider: P,
+    tx_manager: TxManager<P>,
+    castle_address: Address,
+    keepers: BTreeMap<u128, Keeper<P>>,
+}
+
+impl<P> Keepers<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    pub fn new(keepers: Vec<Keeper<P>>) -> eyre::Result<Self> {
+        let first = keepers.first().ok_or_eyre("No Index to keep")?;
+        let provider = first.provider.clone();
+        let tx_manager = first.tx_manager.clone();
+        let castle_address = first.castle_address;
+        let count = keepers.len();
+        let keepers: BTreeMap<_, _> = keepers.into_iter().map(|x| (x.index_id, x)).collect();
+        if keepers.len() != count {
+            bail!("Index is given more than once")
+        }
+        Ok(Self {
+            provider,
+            tx_manager,
+            castle_address,
+            keepers,
+        })
+    }
+
+    pub fn get_address(&self) -> Address {
+        self.provider.default_signer_address()
+    }
+
+    pub fn get_index_ids(&self) -> Vec<u128> {
+        self.keepers.keys().copied().collect()
+    }
+
+    pub fn get(&self, index_id: u128) -> Option<&Keeper<P>> {
+        self.keepers.get(&index_id)
+    }
+
+    pub fn get_mut(&mut self, index_id: u128) -> Option<&mut Keeper<P>> {
+        self.keepers.get_mut(&index_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Keeper<P>> {
+        self.keepers.values()
+    }
+
+    /// Vaults of all Indexes, whose events are to be observed
+    pub fn get_vault_addresses(&self) -> Vec<Address> {
+        self.keepers.values().map(|x| x.vault_address).collect()
+    }
+
+    /// Assets of given Indexes, each listed once
+    pub fn get_assets(&self, index_ids: &[u128]) -> Labels {
+        Labels::from_vec_u128(
+            index_ids
+                .iter()
+                .filter_map(|index_id| self.keepers.get(index_id))
+                .flat_map(|x| x.assets.data.iter().copied())
+                .sorted()
+                .dedup()
+                .collect(),
+        )
+    }
+
+    /// Indexes served by given Vendor
+    pub fn get_vendor_index_ids(&self, vendor_id: u128) -> Vec<u128> {
+        self.keepers
+            .values()
+            .filter(|x| x.vendor_id == vendor_id)
+            .map(|x| x.index_id)
+            .collect()
+    }
+
+    /// Update quotes of given Indexes, in single transaction per Vendor
+    pub async fn update_quotes(&mut self, index_ids: &[u128]) -> eyre::Result<()> {
+        let mut by_vendor = BTreeMap::<u128, Vec<u128>>::new();
+        for index_id in index_ids {
+            let keeper = self
+                .get(*index_id)
+                .ok_or_else(|| eyre!("Index {} is not kept", index_id))?;
+            by_vendor
+                .entry(keeper.vendor_id)
+                .or_default()
+                .push(*index_id);
+        }
+
+        for (vendor_id, index_ids) in by_vendor {
+            if let [index_id] = index_ids[..] {
+                if let Some(keeper) = self.get_mut(index_id) {
+                    keeper.update_quote().await?;
+                }
+                continue;
+            }
+
+            info!(%vendor_id, index_ids = ?index_ids, "🏷️  Handle: UpdateMultipleIndexQuotes");
+            let banker = IBanker::new(self.castle_address, &self.provider);
+
+            let update_quotes = banker.updateMultipleIndexQuotes(vendor_id, index_ids);
+
+            self.tx_manager
+                .send_and_confirm(TxRequest::from_call(update_quotes))
+                .await
+                .context("Failed to update quotes")?;
+        }
+
+        Ok(())
+    }
 }
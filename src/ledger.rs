@@ -0,0 +1,690 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::TxHash;
+use eyre::{Context, OptionExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::amount::Amount,
+    inventory::LongShort,
+    market::source::MarketData,
+    venue::execution::{Fill, Side},
+};
+
+/// Amount with sign, e.g. profit or loss
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignedAmount {
+    pub negative: bool,
+    pub amount: Amount,
+}
+
+impl SignedAmount {
+    pub const ZERO: SignedAmount = SignedAmount {
+        negative: false,
+        amount: Amount::ZERO,
+    };
+
+    pub fn positive(amount: Amount) -> Self {
+        Self {
+            negative: false,
+            amount,
+        }
+    }
+
+    /// Difference `a - b`
+    pub fn diff(a: Amount, b: Amount) -> Self {
+        if a.is_less_than(&b) {
+            Self {
+                negative: true,
+                amount: b.saturating_sub(a).unwrap_or(Amount::ZERO),
+            }
+        } else {
+            Self::positive(a.saturating_sub(b).unwrap_or(Amount::ZERO))
+        }
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        if self.negative == rhs.negative {
+            return Some(Self {
+                negative: self.negative,
+                amount: self.amount.checked_add(rhs.amount)?,
+            });
+        }
+        let diff = Self::diff(self.amount, rhs.amount);
+        Some(Self {
+            negative: diff.negative != self.negative && !diff.amount.is_zero(),
+            amount: diff.amount,
+        })
+    }
+
+    pub fn checked_mul(self, rhs: Amount) -> Option<Self> {
+        Some(Self {
+            negative: self.negative,
+            amount: self.amount.checked_mul(rhs)?,
+        })
+    }
+}
+
+impl Display for SignedAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negative && !self.amount.is_zero() {
+            write!(f, "-")?;
+        }
+        Display::fmt(&self.amount, f)
+    }
+}
+
+impl Serialize for SignedAmount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// What happened to the vendor, as recorded in the journal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+// Externally tagged, as serde can't buffer u128 fields of internally tagged enums
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntry {
+    /// Index bought assets from vendor, paying collateral
+    Acquisition {
+        index_id: u128,
+        tx_hash: Option<TxHash>,
+        log_index: Option<u64>,
+        spent: Amount,
+        minted: Amount,
+    },
+    /// Index sold assets back to vendor, receiving collateral
+    Disposal {
+        index_id: u128,
+        tx_hash: Option<TxHash>,
+        log_index: Option<u64>,
+        burned: Amount,
+        gains: Amount,
+    },
+    /// Hedge order filled by venue
+    Fill {
+        order_id: u64,
+        asset: u128,
+        side: Side,
+        quantity: Amount,
+        price: Amount,
+    },
+    /// Supply submitted on-chain
+    Supply {
+        asset: u128,
+        long: Amount,
+        short: Amount,
+    },
+    /// Latest market prices of assets in the ledger
+    Marks { prices: Vec<(u128, Amount)> },
+}
+
+impl LedgerEntry {
+    fn event_key(&self) -> Option<(TxHash, u64)> {
+        match self {
+            LedgerEntry::Acquisition {
+                tx_hash: Some(tx_hash),
+                log_index: Some(log_index),
+                ..
+            }
+            | LedgerEntry::Disposal {
+                tx_hash: Some(tx_hash),
+                log_index: Some(log_index),
+                ..
+            } => Some((*tx_hash, *log_index)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerRecord {
+    /// Unix time of recording, in seconds
+    pub timestamp: u64,
+    pub entry: LedgerEntry,
+}
+
+/// Hedge position of single asset, with average price of opening it
+#[derive(Debug, Clone, Copy)]
+pub struct AssetBook {
+    pub short: bool,
+    pub quantity: Amount,
+    pub average_price: Amount,
+    pub realized: SignedAmount,
+    pub bought: Amount,
+    pub sold: Amount,
+    pub supply: LongShort,
+    pub mark: Option<Amount>,
+}
+
+impl AssetBook {
+    fn new() -> Self {
+        Self {
+            short: false,
+            quantity: Amount::ZERO,
+            average_price: Amount::ZERO,
+            realized: SignedAmount::ZERO,
+            bought: Amount::ZERO,
+            sold: Amount::ZERO,
+            supply: LongShort::ZERO,
+            mark: None,
+        }
+    }
+
+    pub fn position(&self) -> SignedAmount {
+        SignedAmount {
+            negative: self.short,
+            amount: self.quantity,
+        }
+    }
+
+    fn apply_fill(&mut self, side: Side, quantity: Amount, price: Amount) -> Option<()> {
+        match side {
+            Side::Buy => self.bought = self.bought.checked_add(quantity)?,
+            Side::Sell => self.sold = self.sold.checked_add(quantity)?,
+        }
+
+        let opening = self.quantity.is_zero() || self.short == (side == Side::Sell);
+        if opening {
+            let cost = self
+                .average_price
+                .checked_mul(self.quantity)?
+                .checked_add(price.checked_mul(quantity)?)?;
+            self.quantity = self.quantity.checked_add(quantity)?;
+            self.average_price = cost.checked_div(self.quantity)?;
+            self.short = side == Side::Sell;
+            return Some(());
+        }
+
+        let closing = self.quantity.min(quantity);
+        let pnl = if self.short {
+            SignedAmount::diff(self.average_price, price)
+        } else {
+            SignedAmount::diff(price, self.average_price)
+        };
+        self.realized = self.realized.checked_add(pnl.checked_mul(closing)?)?;
+        self.quantity = self.quantity.saturating_sub(closing)?;
+
+        let remaining = quantity.saturating_sub(closing)?;
+        if !remaining.is_zero() {
+            // Position flipped to the other side
+            self.short = !self.short;
+            self.quantity = remaining;
+            self.average_price = price;
+        } else if self.quantity.is_zero() {
+            self.average_price = Amount::ZERO;
+        }
+        Some(())
+    }
+
+    /// Profit or loss of open position, if it was closed at mark price
+    pub fn unrealized(&self) -> Option<SignedAmount> {
+        let mark = self.mark?;
+        let pnl = if self.short {
+            SignedAmount::diff(self.average_price, mark)
+        } else {
+            SignedAmount::diff(mark, self.average_price)
+        };
+        pnl.checked_mul(self.quantity)
+    }
+}
+
+/// Collateral and ITP flows between vendor and single Index
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct IndexFlows {
+    pub index_id: u128,
+    pub acquisitions: u64,
+    pub disposals: u64,
+    pub spent: Amount,
+    pub minted: Amount,
+    pub burned: Amount,
+    pub gains: Amount,
+}
+
+impl IndexFlows {
+    fn new(index_id: u128) -> Self {
+        Self {
+            index_id,
+            acquisitions: 0,
+            disposals: 0,
+            spent: Amount::ZERO,
+            minted: Amount::ZERO,
+            burned: Amount::ZERO,
+            gains: Amount::ZERO,
+        }
+    }
+
+    /// Collateral received for acquisitions less collateral paid out for disposals
+    pub fn net_collateral(&self) -> SignedAmount {
+        SignedAmount::diff(self.spent, self.gains)
+    }
+}
+
+/// Inventory and PnL of the vendor, journaled to JSONL file (when configured)
+///
+/// Journal is replayed when opened, so that ledger carries over restarts.
+pub struct Ledger {
+    path: Option<PathBuf>,
+    books: BTreeMap<u128, AssetBook>,
+    flows: BTreeMap<u128, IndexFlows>,
+    seen_events: HashSet<(TxHash, u64)>,
+    records: usize,
+}
+
+impl Ledger {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            books: BTreeMap::new(),
+            flows: BTreeMap::new(),
+            seen_events: HashSet::new(),
+            records: 0,
+        }
+    }
+
+    /// Open ledger, replaying its journal if it exists
+    pub fn open(path: Option<PathBuf>) -> eyre::Result<Self> {
+        let mut ledger = Self::new(path);
+        if let Some(path) = ledger.path.clone()
+            && path.exists()
+        {
+            ledger.replay(&path)?;
+        }
+        Ok(ledger)
+    }
+
+    fn replay(&mut self, path: &Path) -> eyre::Result<()> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open ledger: {}", path.display()))?;
+        for (number, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: LedgerRecord = serde_json::from_str(&line).with_context(|| {
+                format!("Invalid ledger record at {}:{}", path.display(), number + 1)
+            })?;
+            self.apply(&record.entry)?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, entry: &LedgerEntry) -> eyre::Result<bool> {
+        if let Some(key) = entry.event_key()
+            && !self.seen_events.insert(key)
+        {
+            return Ok(false);
+        }
+
+        match entry {
+            LedgerEntry::Acquisition {
+                index_id,
+                spent,
+                minted,
+                ..
+            } => {
+                let flows = self
+                    .flows
+                    .entry(*index_id)
+                    .or_insert_with(|| IndexFlows::new(*index_id));
+                flows.acquisitions += 1;
+                flows.spent = flows.spent.checked_add(*spent).ok_or_eyre("Overflow")?;
+                flows.minted = flows.minted.checked_add(*minted).ok_or_eyre("Overflow")?;
+            }
+            LedgerEntry::Disposal {
+                index_id,
+                burned,
+                gains,
+                ..
+            } => {
+                let flows = self
+                    .flows
+                    .entry(*index_id)
+                    .or_insert_with(|| IndexFlows::new(*index_id));
+                flows.disposals += 1;
+                flows.burned = flows.burned.checked_add(*burned).ok_or_eyre("Overflow")?;
+                flows.gains = flows.gains.checked_add(*gains).ok_or_eyre("Overflow")?;
+            }
+            LedgerEntry::Fill {
+                asset,
+                side,
+                quantity,
+                price,
+                ..
+            } => {
+                self.books
+                    .entry(*asset)
+                    .or_insert_with(AssetBook::new)
+                    .apply_fill(*side, *quantity, *price)
+                    .ok_or_eyre("Overflow")?;
+            }
+            LedgerEntry::Supply { asset, long, short } => {
+                self.books
+                    .entry(*asset)
+                    .or_insert_with(AssetBook::new)
+                    .supply = LongShort {
+                    long: *long,
+                    short: *short,
+                };
+            }
+            LedgerEntry::Marks { prices } => {
+                for (asset, price) in prices {
+                    if let Some(book) = self.books.get_mut(asset) {
+                        book.mark = Some(*price);
+                    }
+                }
+            }
+        }
+
+        self.records += 1;
+        Ok(true)
+    }
+
+    /// Append entry to the journal and apply it (duplicate events are ignored)
+    ///
+    /// Journal is written first, so that entry failing to persist leaves
+    /// ledger unchanged, and can be recorded again.
+    pub fn record(&mut self, entry: LedgerEntry) -> eyre::Result<()> {
+        if let Some(key) = entry.event_key()
+            && self.seen_events.contains(&key)
+        {
+            return Ok(());
+        }
+
+        if let Some(path) = &self.path {
+            let record = LedgerRecord {
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                entry: entry.clone(),
+            };
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open ledger: {}", path.display()))?;
+            let line = serde_json::to_string(&record)?;
+            writeln!(file, "{}", line)
+                .with_context(|| format!("Failed to write ledger: {}", path.display()))?;
+        }
+
+        self.apply(&entry)?;
+        Ok(())
+    }
+
+    pub fn record_fill(&mut self, fill: &Fill) -> eyre::Result<()> {
+        if fill.quantity.is_zero() {
+            return Ok(());
+        }
+        self.record(LedgerEntry::Fill {
+            order_id: fill.order_id,
+            asset: fill.asset,
+            side: fill.side,
+            quantity: fill.quantity,
+            price: fill.price,
+        })
+    }
+
+    pub fn record_supply(&mut self, supply: &[(u128, LongShort)]) -> eyre::Result<()> {
+        for (asset, quantity) in supply {
+            self.record(LedgerEntry::Supply {
+                asset: *asset,
+                long: quantity.long,
+                short: quantity.short,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Record prices of assets in the ledger (others are ignored)
+    pub fn record_marks(
+        &mut self,
+        assets: &[u128],
+        market_data: &[MarketData],
+    ) -> eyre::Result<()> {
+        let prices: Vec<_> = assets
+            .iter()
+            .zip(market_data)
+            .filter(|(asset, _)| self.books.contains_key(asset))
+            .map(|(asset, data)| (*asset, data.price))
+            .collect();
+        if prices.is_empty() {
+            return Ok(());
+        }
+        self.record(LedgerEntry::Marks { prices })
+    }
+
+    pub fn get_book(&self, asset: u128) -> Option<&AssetBook> {
+        self.books.get(&asset)
+    }
+
+    pub fn report(&self) -> LedgerReport {
+        let assets: Vec<_> = self
+            .books
+            .iter()
+            .map(|(asset, book)| AssetReport {
+                asset: *asset,
+                position: book.position(),
+                average_price: book.average_price,
+                mark: book.mark,
+                realized: book.realized,
+                unrealized: book.unrealized(),
+                bought: book.bought,
+                sold: book.sold,
+                supply_long: book.supply.long,
+                supply_short: book.supply.short,
+            })
+            .collect();
+
+        let sum = |pnl: Vec<SignedAmount>| {
+            pnl.into_iter()
+                .try_fold(SignedAmount::ZERO, |acc, x| acc.checked_add(x))
+        };
+        let realized = sum(assets.iter().map(|x| x.realized).collect());
+        let unrealized = sum(assets.iter().filter_map(|x| x.unrealized).collect());
+
+        LedgerReport {
+            records: self.records,
+            total: realized.zip(unrealized).and_then(|(a, b)| a.checked_add(b)),
+            realized,
+            unrealized,
+            assets,
+            indexes: self.flows.values().copied().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetReport {
+    pub asset: u128,
+    /// Net position, negative when short
+    pub position: SignedAmount,
+    pub average_price: Amount,
+    pub mark: Option<Amount>,
+    pub realized: SignedAmount,
+    /// Not known until mark price of the asset is recorded
+    pub unrealized: Option<SignedAmount>,
+    pub bought: Amount,
+    pub sold: Amount,
+    pub supply_long: Amount,
+    pub supply_short: Amount,
+}
+
+/// Snapshot of the ledger, printed by `ledger` command or exported as JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerReport {
+    pub records: usize,
+    pub realized: Option<SignedAmount>,
+    pub unrealized: Option<SignedAmount>,
+    pub total: Option<SignedAmount>,
+    pub assets: Vec<AssetReport>,
+    pub indexes: Vec<IndexFlows>,
+}
+
+impl Display for LedgerReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |x: Option<SignedAmount>| x.map_or("-".to_owned(), |x| format!("{:.6}", x));
+
+        writeln!(
+            f,
+            "{:>10} {:>16} {:>14} {:>14} {:>14} {:>14} {:>16}",
+            "asset", "position", "avg price", "mark", "realized", "unrealized", "supply"
+        )?;
+        for x in &self.assets {
+            let supply = SignedAmount::diff(x.supply_long, x.supply_short);
+            writeln!(
+                f,
+                "{:>10} {:>16} {:>14} {:>14} {:>14} {:>14} {:>16}",
+                x.asset,
+                format!("{:.6}", x.position),
+                format!("{:.6}", x.average_price),
+                x.mark.map_or("-".to_owned(), |x| format!("{:.6}", x)),
+                format!("{:.6}", x.realized),
+                show(x.unrealized),
+                format!("{:.6}", supply),
+            )?;
+        }
+        writeln!(f)?;
+        for x in &self.indexes {
+            writeln!(
+                f,
+                "index {}: {} acquisitions (spent {:.6}, minted {:.6}), {} disposals (burned {:.6}, gains {:.6}), net collateral {:.6}",
+                x.index_id,
+                x.acquisitions,
+                x.spent,
+                x.minted,
+                x.disposals,
+                x.burned,
+                x.gains,
+                x.net_collateral()
+            )?;
+        }
+        write!(
+            f,
+            "PnL: realized {}, unrealized {}, total {} ({} records)",
+            show(self.realized),
+            show(self.unrealized),
+            show(self.total),
+            self.records
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn amount(x: &str) -> Amount {
+        x.parse().unwrap()
+    }
+
+    fn fill(asset: u128, side: Side, quantity: &str, price: &str) -> LedgerEntry {
+        LedgerEntry::Fill {
+            order_id: 0,
+            asset,
+            side,
+            quantity: amount(quantity),
+            price: amount(price),
+        }
+    }
+
+    #[test]
+    fn test_ledger_pnl() {
+        let mut ledger = Ledger::new(None);
+        ledger.record(fill(101, Side::Buy, "10", "100")).unwrap();
+        ledger.record(fill(101, Side::Buy, "10", "110")).unwrap();
+
+        let book = ledger.get_book(101).unwrap();
+        assert_eq!(book.average_price, amount("105"));
+
+        // Sell more than held, closing long and opening short
+        ledger.record(fill(101, Side::Sell, "25", "100")).unwrap();
+        let book = ledger.get_book(101).unwrap();
+        assert_eq!(book.realized.to_string(), "-100.0");
+        assert_eq!(book.position().to_string(), "-5.0");
+        assert_eq!(book.average_price, amount("100"));
+
+        ledger
+            .record(LedgerEntry::Marks {
+                prices: vec![(101, amount("90")), (102, amount("1"))],
+            })
+            .unwrap();
+        assert_eq!(
+            ledger.get_book(101).unwrap().unrealized(),
+            Some(SignedAmount::positive(amount("50")))
+        );
+        assert!(ledger.get_book(102).is_none());
+
+        let acquisition = LedgerEntry::Acquisition {
+            index_id: 1001,
+            tx_hash: Some(TxHash::repeat_byte(1)),
+            log_index: Some(3),
+            spent: amount("1000"),
+            minted: amount("9.5"),
+        };
+        ledger.record(acquisition.clone()).unwrap();
+        ledger.record(acquisition).unwrap();
+
+        let report = ledger.report();
+        assert_eq!(report.indexes[0].acquisitions, 1);
+        assert_eq!(report.total.unwrap().to_string(), "-50.0");
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["assets"][0]["position"], "-5.0");
+    }
+
+    #[test]
+    fn test_ledger_journal() {
+        let path = std::env::temp_dir().join(format!("ledger-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut ledger = Ledger::open(Some(path.clone())).unwrap();
+        ledger.record(fill(101, Side::Buy, "2", "50")).unwrap();
+        ledger
+            .record(LedgerEntry::Marks {
+                prices: vec![(101, amount("55"))],
+            })
+            .unwrap();
+
+        let ledger = Ledger::open(Some(path.clone())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let book = ledger.get_book(101).unwrap();
+        assert_eq!(book.quantity, amount("2"));
+        assert_eq!(
+            book.unrealized(),
+            Some(SignedAmount::positive(amount("10")))
+        );
+    }
+
+    #[test]
+    fn test_ledger_journal_failure() {
+        let path = std::env::temp_dir().join("conveyor-missing-dir/ledger.jsonl");
+        let mut ledger = Ledger::new(Some(path));
+
+        let acquisition = LedgerEntry::Acquisition {
+            index_id: 1001,
+            tx_hash: Some(TxHash::repeat_byte(1)),
+            log_index: Some(3),
+            spent: amount("1000"),
+            minted: amount("9.5"),
+        };
+        assert!(ledger.record(acquisition.clone()).is_err());
+        assert!(ledger.record(fill(101, Side::Buy, "2", "50")).is_err());
+
+        // Nothing applied, so events are not taken as seen either
+        assert!(ledger.get_book(101).is_none());
+        assert!(ledger.report().indexes.is_empty());
+
+        ledger.path = None;
+        ledger.record(acquisition).unwrap();
+        assert_eq!(ledger.report().indexes[0].acquisitions, 1);
+    }
+}
@@ -25,7 +25,37 @@ pub mod common {
     pub mod vector;
 }
 
+pub mod market {
+    pub mod file_replay;
+    pub mod random;
+    pub mod source;
+    pub mod static_file;
+}
+
+pub mod venue {
+    pub mod execution;
+    pub mod instant;
+    pub mod simulated;
+}
+
 pub mod app;
+pub mod checkpoint;
+pub mod chunking;
+pub mod config;
+pub mod confirmations;
+pub mod control;
+pub mod dead_letter;
+pub mod error;
+pub mod hedging;
+pub mod inventory;
 pub mod keeper;
+pub mod ledger;
+pub mod metrics;
+pub mod order;
+pub mod preflight;
 pub mod pulley;
+pub mod retry;
+pub mod roles;
+pub mod trader;
+pub mod tx_manager;
 pub mod vendor;
@@ -4,50 +4,214 @@ use alloy::{
     providers::{Provider, ProviderBuilder, WalletProvider},
     signers::local::PrivateKeySigner,
 };
-use clap::Parser;
-use conveyor::{app::App, keeper::Keeper, pulley::Pulley, vendor::Vendor};
-use eyre::bail;
+use clap::{Args, Parser, Subcommand};
+use conveyor::{
+    app::App,
+    checkpoint::CheckpointHandle,
+    common::amount::Amount,
+    config::{Config, VendorConfig},
+    control::{self, ControlHandle},
+    hedging::Hedger,
+    keeper::{Keeper, Keepers},
+    ledger::Ledger,
+    metrics::{self, Metrics},
+    preflight::{PreflightReport, SignerRoles},
+    pulley::Pulley,
+    roles::Role,
+    trader::Trader,
+    tx_manager::TxManager,
+    vendor::{Vendor, Vendors},
+};
+use eyre::{Context, bail, eyre};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::PathBuf,
+};
 use tokio::{
     signal::unix::{SignalKind, signal},
     sync::mpsc::unbounded_channel,
 };
 use tokio_util::sync::CancellationToken;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 // --- 2. CLI Arguments ---
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Conveyor: Off-chain client for VaultWorks")]
-struct Args {
-    #[arg(long, env = "RPC_URL", default_value = "http://localhost:8547")]
-    rpc_url: String,
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
 
+#[derive(Args, Debug)]
+struct CommonArgs {
+    /// Configuration file (TOML or YAML), overridden by flags
+    #[arg(long, env = "CONVEYOR_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[arg(long, env = "RPC_URL")]
+    rpc_url: Option<String>,
+
+    /// Key of the trader, and of Keeper and Vendor unless their own keys are given
     #[arg(long, env = "PRIVATE_KEY")]
-    private_key: String,
+    private_key: Option<String>,
+
+    /// Key holding KEEPER_ROLE (and ISSUER_ROLE to submit Indexes)
+    #[arg(long, env = "KEEPER_PRIVATE_KEY")]
+    keeper_private_key: Option<String>,
+
+    /// Key holding VENDOR_ROLE
+    #[arg(long, env = "VENDOR_PRIVATE_KEY")]
+    vendor_private_key: Option<String>,
 
     #[arg(long, env = "CASTLE_ADDRESS")]
-    castle_address: Address,
+    castle_address: Option<Address>,
 
     #[arg(long, env = "CUSTODY_ADDRESS")]
-    custody_address: Address,
+    custody_address: Option<Address>,
 
     #[arg(long, env = "COLLATERAL_ADDRESS")]
-    collateral_address: Address,
+    collateral_address: Option<Address>,
 
-    #[arg(long, default_value = "1")]
-    vendor_id: u128,
+    #[arg(long)]
+    vendor_id: Option<u128>,
 
-    #[arg(long, default_value = "1001")]
-    index_id: u128,
+    #[arg(long)]
+    index_id: Option<u128>,
+
+    /// Maximum number of assets submitted in single transaction
+    #[arg(long)]
+    chunk_size: Option<usize>,
 
-    #[arg(long, default_value = "5")]
-    market_size: usize,
+    /// Only simulate state-changing calls of Keeper and Vendor, and log values they return
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run Vendor-Keeper daemon replying to on-chain events
+    Run {
+        #[arg(long)]
+        market_size: Option<usize>,
+
+        #[arg(long)]
+        index_size: Option<usize>,
+
+        /// Attach to existing Vendor and Index instead of submitting new ones
+        #[arg(long, default_value_t = false)]
+        attach: bool,
+
+        /// File storing last processed block
+        #[arg(long, env = "CHECKPOINT_FILE")]
+        checkpoint_file: Option<PathBuf>,
+
+        /// Backfill events starting from this block instead of checkpoint
+        #[arg(long)]
+        from_block: Option<u64>,
+
+        /// Serve metrics and control API on this address, e.g. 127.0.0.1:9100
+        #[arg(long, env = "HTTP_LISTEN")]
+        http_listen: Option<SocketAddr>,
+    },
+    /// Submit Vendor assets, margin, market data and supply
+    SetupVendor {
+        #[arg(long)]
+        market_size: Option<usize>,
+    },
+    /// Submit new Index using assets of existing Vendor
+    CreateIndex {
+        #[arg(long)]
+        index_size: Option<usize>,
+    },
+    /// Show Index, quote and orders
+    Status {
+        /// Show order and balance of this trader
+        #[arg(long)]
+        trader: Option<Address>,
+
+        /// Show claims against this keeper
+        #[arg(long, env = "KEEPER_ADDRESS")]
+        keeper: Option<Address>,
+    },
+    /// Claim filled portion of pending order (as trader)
+    Claim {
+        #[command(subcommand)]
+        side: ClaimSide,
+    },
+    /// Place order to Vault (as trader)
+    Order {
+        #[command(subcommand)]
+        side: OrderSide,
+    },
+    /// Show Vendor inventory and PnL from the ledger file
+    Ledger {
+        /// Ledger file (overrides `ledger_file` in `[vendor]`)
+        #[arg(long, env = "LEDGER_FILE")]
+        ledger_file: Option<PathBuf>,
+
+        /// Print report as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+
+        /// Write report as JSON to this file
+        #[arg(long)]
+        export: Option<PathBuf>,
+    },
+}
 
-    #[arg(long, default_value = "3")]
-    index_size: usize,
+#[derive(Subcommand, Debug)]
+enum ClaimSide {
+    /// Claim ITP acquired with collateral
+    Acquisition {
+        #[arg(long, env = "KEEPER_ADDRESS")]
+        keeper: Address,
+
+        /// Collateral amount (raw, 18 decimals)
+        #[arg(long)]
+        amount: u128,
+    },
+    /// Claim collateral gained by disposing ITP
+    Disposal {
+        #[arg(long, env = "KEEPER_ADDRESS")]
+        keeper: Address,
+
+        /// ITP amount (raw, 18 decimals)
+        #[arg(long)]
+        amount: u128,
+    },
+}
 
-    #[arg(long, default_value = "500")]
-    chunk_size: usize,
+#[derive(Subcommand, Debug)]
+enum OrderSide {
+    /// Place Buy order spending collateral
+    Buy {
+        #[arg(long, env = "KEEPER_ADDRESS")]
+        keeper: Address,
+
+        /// Collateral amount (raw, 18 decimals)
+        #[arg(long)]
+        amount: u128,
+
+        #[arg(long, default_value_t = false)]
+        instant_fill: bool,
+    },
+    /// Place Sell order disposing ITP
+    Sell {
+        #[arg(long, env = "KEEPER_ADDRESS")]
+        keeper: Address,
+
+        /// ITP amount (raw, 18 decimals)
+        #[arg(long)]
+        amount: u128,
+
+        #[arg(long, default_value_t = false)]
+        instant_fill: bool,
+    },
 }
 
 async fn with_provider(
@@ -62,9 +226,21 @@ async fn with_provider(
         .connect(rpc_url.as_str())
         .await?;
 
+    info!(
+        wallet= %provider.default_signer_address(),
+        "🔌 Provider connected"
+    );
+
     Ok(provider)
 }
 
+/// Key of given role, falling back to `PRIVATE_KEY`
+fn private_key(key: &Option<String>, args: &CommonArgs, name: &str) -> eyre::Result<String> {
+    key.clone()
+        .or_else(|| args.private_key.clone())
+        .ok_or_else(|| eyre!("Neither {} nor PRIVATE_KEY is set", name))
+}
+
 fn init_tracing() {
     tracing_subscriber::registry()
         .with(fmt::layer())
@@ -72,74 +248,357 @@ fn init_tracing() {
         .init();
 }
 
-#[tokio::main]
-async fn main() -> eyre::Result<()> {
-    init_tracing();
+fn load_config(args: &CommonArgs) -> eyre::Result<(Config, u128)> {
+    let mut config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
 
-    let args = Args::try_parse()?;
+    if let Some(rpc_url) = &args.rpc_url {
+        config.network.rpc_url = rpc_url.clone();
+    }
+    if let Some(castle_address) = args.castle_address {
+        config.network.castle_address = Some(castle_address);
+    }
+    if let Some(custody_address) = args.custody_address {
+        config.network.custody_address = Some(custody_address);
+    }
+    if let Some(collateral_address) = args.collateral_address {
+        config.network.collateral_address = Some(collateral_address);
+    }
+    if let Some(vendor_id) = args.vendor_id {
+        config.vendor.vendor_id = vendor_id;
+    }
+    if let Some(chunk_size) = args.chunk_size {
+        config.vendor.chunk_size = chunk_size;
+    }
+    if args.dry_run {
+        config.tx.dry_run = true;
+    }
 
-    let provider = with_provider(args.rpc_url, args.private_key).await?;
+    let index_id = args.index_id.unwrap_or_else(|| config.default_index_id());
+    config.index_entry(index_id);
 
-    info!(
-        wallet= %provider.default_signer_address(),
-        "🔌 Provider connected"
-    );
+    config.validate()?;
+    Ok((config, index_id))
+}
 
-    let mut keeper = Keeper::new(
+/// Setup can't be simulated, since each step depends on state changed by previous ones
+fn ensure_no_dry_run_setup(config: &Config) -> eyre::Result<()> {
+    if config.tx.dry_run {
+        bail!(
+            "Setup of Vendor or Index can't be simulated with --dry-run, use `run --attach` instead"
+        )
+    }
+    Ok(())
+}
+
+fn new_keeper<P>(
+    provider: &P,
+    tx_manager: &TxManager<P>,
+    config: &Config,
+    index_id: u128,
+) -> eyre::Result<Keeper<P>>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let index = config.index(index_id);
+    Ok(Keeper::new(
         provider.clone(),
-        args.castle_address,
-        args.custody_address,
-        args.collateral_address,
-        args.index_id,
-        args.vendor_id,
-    );
+        tx_manager.clone(),
+        config.network.castle_address()?,
+        config.network.custody_address(&index)?,
+        config.network.collateral_address(&index)?,
+        index_id,
+        config.index_vendor_id(&index),
+    ))
+}
 
-    let mut vendor = Vendor::new(
+/// Vendor using custody and collateral of given Index
+fn new_vendor<P>(
+    provider: &P,
+    tx_manager: &TxManager<P>,
+    config: &Config,
+    vendor_config: &VendorConfig,
+    index_id: u128,
+) -> eyre::Result<Vendor<P>>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let index = config.index(index_id);
+    Ok(Vendor::new(
         provider.clone(),
-        args.castle_address,
-        args.custody_address,
-        args.collateral_address,
-        args.vendor_id,
-        args.chunk_size,
-    );
+        tx_manager.clone(),
+        config.network.castle_address()?,
+        config.network.custody_address(&index)?,
+        config.network.collateral_address(&index)?,
+        vendor_config.vendor_id,
+        vendor_config.chunk_size,
+        vendor_config.chunking.clone(),
+        vendor_config.market_data.build()?,
+        vendor_config.hedging.venue.build(),
+        Hedger::new(&vendor_config.hedging),
+        Ledger::open(vendor_config.ledger_file.clone())?,
+    ))
+}
+
+/// Private key of the Vendor from its own environment variable, if one is configured
+fn vendor_private_key(vendor_config: &VendorConfig) -> eyre::Result<Option<String>> {
+    vendor_config
+        .private_key_env
+        .as_ref()
+        .map(|name| {
+            std::env::var(name).with_context(|| {
+                format!(
+                    "Private key of Vendor {} is not set in {}",
+                    vendor_config.vendor_id, name
+                )
+            })
+        })
+        .transpose()
+}
+
+fn new_trader<P>(
+    provider: &P,
+    tx_manager: &TxManager<P>,
+    config: &Config,
+    index_id: u128,
+) -> eyre::Result<Trader<P>>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let index = config.index(index_id);
+    Ok(Trader::new(
+        provider.clone(),
+        tx_manager.clone(),
+        config.network.castle_address()?,
+        config.network.collateral_address(&index)?,
+        index_id,
+    ))
+}
+
+/// Check contracts, and roles and gas of signers, refusing to start when anything is missing
+async fn preflight<P>(
+    provider: &P,
+    config: &Config,
+    index_ids: &[u128],
+    signers: BTreeMap<Address, SignerRoles>,
+) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let castle_address = config.network.castle_address()?;
+
+    let mut contracts = BTreeMap::new();
+    let mut addresses = BTreeMap::new();
+    contracts.insert(castle_address, "Castle".to_owned());
+    for index_id in index_ids {
+        let index = config.index(*index_id);
+        contracts
+            .entry(config.network.collateral_address(&index)?)
+            .or_insert_with(|| format!("Collateral of Index {}", index_id));
+        addresses
+            .entry(config.network.custody_address(&index)?)
+            .or_insert_with(|| format!("Custody of Index {}", index_id));
+    }
+    addresses.retain(|address, _| !contracts.contains_key(address));
+
+    // Simulated calls don't spend gas
+    let min_balance = if config.tx.dry_run {
+        Amount::ZERO
+    } else {
+        config.tx.min_gas_balance
+    };
+
+    let report = PreflightReport::fetch(
+        provider,
+        castle_address,
+        contracts,
+        addresses,
+        signers,
+        min_balance,
+    )
+    .await?;
+
+    if report.is_ok() {
+        info!("🛫 Preflight passed\n{}", report);
+    } else {
+        error!("🛫 Preflight failed\n{}", report);
+        bail!(
+            "Preflight found {} problem(s), fix them before starting",
+            report.problems().len()
+        )
+    }
+
+    Ok(())
+}
+
+async fn run<P, F, R>(
+    keeper_provider: P,
+    vendor_provider: P,
+    config: Config,
+    index_ids: Vec<u128>,
+    attach: bool,
+    connect: F,
+) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+    F: Fn(String) -> R,
+    R: Future<Output = eyre::Result<P>>,
+{
+    let metrics = Metrics::new();
+    let tx_manager =
+        TxManager::new(keeper_provider.clone(), config.tx.clone()).with_metrics(metrics.clone());
 
     info!(
-        castle_address = %args.castle_address,
-        custody_address = %args.custody_address,
-        collateral_address = %args.collateral_address,
-        index_id = %args.index_id,
-        vendor_id = %args.vendor_id,
+        castle_address = %config.network.castle_address()?,
+        index_ids = ?index_ids,
+        vendor_ids = ?config.all_vendors().map(|x| x.vendor_id).collect::<Vec<_>>(),
         "🔧 Configured Keeper & Vendor"
     );
 
-    vendor.setup(args.market_size).await?;
+    // Transactions of the same signer must share nonces
+    let mut tx_managers = HashMap::new();
+    tx_managers.insert(keeper_provider.default_signer_address(), tx_manager.clone());
+
+    let mut vendors = Vec::new();
+    for vendor_config in config.all_vendors() {
+        let vendor_provider = match vendor_private_key(vendor_config)? {
+            Some(private_key) => connect(private_key).await?,
+            None => vendor_provider.clone(),
+        };
+        let vendor_tx_manager = tx_managers
+            .entry(vendor_provider.default_signer_address())
+            .or_insert_with(|| {
+                TxManager::new(vendor_provider.clone(), config.tx.clone())
+                    .with_metrics(metrics.clone())
+            })
+            .clone();
+
+        // Custody and collateral are those of the first Index the Vendor serves
+        let index_id = index_ids
+            .iter()
+            .copied()
+            .find(|x| config.index_vendor_id(&config.index(*x)) == vendor_config.vendor_id)
+            .unwrap_or_else(|| config.default_index_id());
+
+        vendors.push(new_vendor(
+            &vendor_provider,
+            &vendor_tx_manager,
+            &config,
+            vendor_config,
+            index_id,
+        )?);
+    }
 
-    info!(
-        market_size = %args.market_size,
-        "Configured Market"
-    );
+    // Keeper submits Indexes unless attaching to existing ones
+    let mut signers = BTreeMap::<Address, SignerRoles>::new();
+    let keeper_roles = signers
+        .entry(keeper_provider.default_signer_address())
+        .or_default();
+    keeper_roles.required.push(Role::Keeper);
+    if attach {
+        keeper_roles.optional.push(Role::Issuer);
+    } else {
+        keeper_roles.required.push(Role::Issuer);
+    }
+    for vendor in &vendors {
+        signers
+            .entry(vendor.get_address())
+            .or_default()
+            .required
+            .push(Role::Vendor);
+    }
+    preflight(&keeper_provider, &config, &index_ids, signers).await?;
+
+    for (vendor, vendor_config) in vendors.iter_mut().zip(config.all_vendors()) {
+        if attach {
+            vendor.attach().await?;
+        } else {
+            vendor.setup(vendor_config).await?;
+
+            info!(
+                vendor_id = %vendor_config.vendor_id,
+                vendor_address = %vendor.get_address(),
+                market_size = %vendor.get_market_assets().data.len(),
+                "Configured Market"
+            );
+        }
+    }
 
-    keeper.setup(vendor.get_market_assets(), args.index_size).await?;
+    let vendors = Vendors::new(vendors)?;
+
+    let mut keepers = Vec::new();
+    for index_id in index_ids {
+        let mut keeper = new_keeper(&keeper_provider, &tx_manager, &config, index_id)?;
+
+        if attach {
+            keeper.attach().await?;
+        } else {
+            let vendor = vendors
+                .get(keeper.get_vendor_id())
+                .ok_or_else(|| eyre!("Vendor {} is not run", keeper.get_vendor_id()))?;
+            keeper
+                .setup(vendor.get_market_assets(), &config.index(index_id))
+                .await?;
+        }
 
-    let vault_address = keeper.get_vault_address();
-    if vault_address.is_zero() {
-        bail!("Vault address is zero")
+        let vault_address = keeper.get_vault_address();
+        if vault_address.is_zero() {
+            bail!("Vault address of Index {} is zero", index_id)
+        }
+
+        info!(
+            %index_id,
+            vendor_id = %keeper.get_vendor_id(),
+            custody_address = %keeper.get_custody_address(),
+            collateral_address = %keeper.get_collateral_address(),
+            index_size = %keeper.get_assets().data.len(),
+            vault_address = %format!("👉 {}", vault_address),
+            "🏦 Configured Index / Vault"
+        );
+
+        keepers.push(keeper);
     }
 
-    info!(
-        index_size = %args.index_size,
-        vault_address = %format!("👉 {}", vault_address),
-        "🏦 Configured Index / Vault"
-    );
+    let keepers = Keepers::new(keepers)?;
+    let vault_addresses = keepers.get_vault_addresses();
 
     info!("Cranking pulley...");
 
     let (tx, rx) = unbounded_channel();
 
     let cancel_token = CancellationToken::new();
+    let (control_handle, control_rx) = ControlHandle::new(config.clone());
+
+    if let Some(listen) = config.http.listen {
+        if config.http.auth_token.is_none() && !listen.ip().is_loopback() {
+            warn!(%listen, "Control API rejects POST requests, since auth_token is not set");
+        }
+        for tx_manager in tx_managers.values() {
+            tokio::spawn(metrics::poll_gas_balance(
+                tx_manager.get_provider().clone(),
+                metrics.clone(),
+                config.http.balance_poll_interval(),
+                cancel_token.clone(),
+            ));
+        }
+        let router = metrics::router(metrics.clone()).merge(control::router(control_handle));
+        let cancel_token = cancel_token.clone();
+        tokio::spawn(async move {
+            if let Err(err) = metrics::serve(listen, router, cancel_token).await {
+                error!("Error while serving HTTP: {:?}", err);
+            }
+        });
+    }
+
+    let checkpoint = CheckpointHandle::load(&config.pulley)?;
+
     let pulley_task = tokio::spawn(Pulley::run(
-        provider,
-        vault_address,
+        keeper_provider,
+        vault_addresses,
+        config.pulley.clone(),
+        checkpoint.clone(),
         tx,
         cancel_token.clone(),
     ));
@@ -159,9 +618,9 @@ async fn main() -> eyre::Result<()> {
         }
     });
 
-    let mut app = App::new(keeper, vendor);
+    let mut app = App::new(keepers, vendors, config.policy.clone(), metrics, checkpoint);
 
-    if let Err(err) = app.run(rx, cancel_token.clone()).await {
+    if let Err(err) = app.run(rx, control_rx, cancel_token.clone()).await {
         error!("Error while running app: {:?}", err);
     }
 
@@ -172,3 +631,282 @@ async fn main() -> eyre::Result<()> {
 
     Ok(())
 }
+
+async fn setup_vendor<P>(provider: P, config: Config, index_id: u128) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let mut signers = BTreeMap::new();
+    signers.insert(
+        provider.default_signer_address(),
+        SignerRoles::one_shot(vec![Role::Vendor]),
+    );
+    preflight(&provider, &config, &[index_id], signers).await?;
+
+    let tx_manager = TxManager::new(provider.clone(), config.tx.clone());
+    let mut vendor = new_vendor(&provider, &tx_manager, &config, &config.vendor, index_id)?;
+
+    vendor.setup(&config.vendor).await?;
+
+    info!(
+        vendor_id = %config.vendor.vendor_id,
+        market_size = %vendor.get_market_assets().data.len(),
+        "Configured Market"
+    );
+
+    Ok(())
+}
+
+async fn create_index<P>(provider: P, config: Config, index_id: u128) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let mut signers = BTreeMap::new();
+    signers.insert(
+        provider.default_signer_address(),
+        SignerRoles::one_shot(vec![Role::Keeper, Role::Issuer]),
+    );
+    preflight(&provider, &config, &[index_id], signers).await?;
+
+    let tx_manager = TxManager::new(provider.clone(), config.tx.clone());
+    let mut keeper = new_keeper(&provider, &tx_manager, &config, index_id)?;
+    let vendor_config = config
+        .vendor_config(keeper.get_vendor_id())
+        .ok_or_else(|| eyre!("Vendor {} is not declared", keeper.get_vendor_id()))?;
+    let mut vendor = new_vendor(&provider, &tx_manager, &config, vendor_config, index_id)?;
+
+    vendor.attach().await?;
+    keeper
+        .setup(vendor.get_market_assets(), &config.index(index_id))
+        .await?;
+
+    info!(
+        index_id = %index_id,
+        index_size = %keeper.get_assets().data.len(),
+        vault_address = %format!("👉 {}", keeper.get_vault_address()),
+        "🏦 Configured Index / Vault"
+    );
+
+    Ok(())
+}
+
+async fn status<P>(
+    provider: P,
+    config: Config,
+    index_id: u128,
+    trader_address: Option<Address>,
+    keeper_address: Option<Address>,
+) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let tx_manager = TxManager::new(provider.clone(), config.tx.clone());
+    let mut keeper = new_keeper(&provider, &tx_manager, &config, index_id)?;
+    let mut trader = new_trader(&provider, &tx_manager, &config, index_id)?;
+
+    keeper.attach().await?;
+    trader.attach().await?;
+
+    keeper.log_status().await?;
+    keeper.log_pending_order().await?;
+
+    if let Some(trader_address) = trader_address {
+        keeper.log_trader_order(trader_address).await?;
+        trader.log_balance(trader_address).await?;
+
+        if let Some(keeper_address) = keeper_address {
+            trader.log_claims(keeper_address, trader_address).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn claim<P>(provider: P, config: Config, index_id: u128, side: ClaimSide) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let tx_manager = TxManager::new(provider.clone(), config.tx.clone());
+    let mut trader = new_trader(&provider, &tx_manager, &config, index_id)?;
+    let trader_address = provider.default_signer_address();
+
+    trader.attach().await?;
+
+    match side {
+        ClaimSide::Acquisition { keeper, amount } => {
+            trader.log_claims(keeper, trader_address).await?;
+            trader.claim_acquisition(keeper, amount).await?;
+            trader.log_claims(keeper, trader_address).await?;
+        }
+        ClaimSide::Disposal { keeper, amount } => {
+            trader.log_claims(keeper, trader_address).await?;
+            trader.claim_disposal(keeper, amount).await?;
+            trader.log_claims(keeper, trader_address).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn order<P>(provider: P, config: Config, index_id: u128, side: OrderSide) -> eyre::Result<()>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let tx_manager = TxManager::new(provider.clone(), config.tx.clone());
+    let mut trader = new_trader(&provider, &tx_manager, &config, index_id)?;
+    let trader_address = provider.default_signer_address();
+
+    trader.attach().await?;
+
+    match side {
+        OrderSide::Buy {
+            keeper,
+            amount,
+            instant_fill,
+        } => {
+            trader.place_buy_order(keeper, amount, instant_fill).await?;
+            trader.log_claims(keeper, trader_address).await?;
+        }
+        OrderSide::Sell {
+            keeper,
+            amount,
+            instant_fill,
+        } => {
+            trader
+                .place_sell_order(keeper, amount, instant_fill)
+                .await?;
+            trader.log_claims(keeper, trader_address).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn ledger(
+    config: Config,
+    ledger_file: Option<PathBuf>,
+    json: bool,
+    export: Option<PathBuf>,
+) -> eyre::Result<()> {
+    let Some(path) = ledger_file.or(config.vendor.ledger_file) else {
+        bail!("No ledger file, set `ledger_file` in [vendor] section or pass --ledger-file")
+    };
+    if !path.exists() {
+        bail!("Ledger file not found: {}", path.display())
+    }
+
+    let report = Ledger::open(Some(path))?.report();
+
+    if let Some(export) = export {
+        std::fs::write(&export, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to export ledger: {}", export.display()))?;
+        info!(path = %export.display(), "📒 Ledger exported");
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    init_tracing();
+
+    let cli = Cli::try_parse()?;
+    let (mut config, index_id) = load_config(&cli.common)?;
+
+    let rpc_url = config.network.rpc_url.clone();
+    let args = &cli.common;
+    let keeper_key = || private_key(&args.keeper_private_key, args, "KEEPER_PRIVATE_KEY");
+    let vendor_key = || private_key(&args.vendor_private_key, args, "VENDOR_PRIVATE_KEY");
+    let trader_key = || private_key(&None, args, "PRIVATE_KEY");
+
+    // Ledger is read from file only, so it sends no transactions
+    if config.tx.dry_run && !matches!(cli.command, Command::Ledger { .. }) {
+        warn!("🧪 Dry-run: transactions are only simulated, no state is changed on-chain");
+    }
+
+    match cli.command {
+        Command::Run {
+            market_size,
+            index_size,
+            attach,
+            checkpoint_file,
+            from_block,
+            http_listen,
+        } => {
+            if let Some(http_listen) = http_listen {
+                config.http.listen = Some(http_listen);
+            }
+            if let Some(checkpoint_file) = checkpoint_file {
+                config.pulley.checkpoint_file = Some(checkpoint_file);
+            }
+            if let Some(from_block) = from_block {
+                config.pulley.from_block = Some(from_block);
+            }
+            if let Some(market_size) = market_size {
+                config.vendor.market_size = market_size;
+            }
+            if let Some(index_size) = index_size {
+                config.index_entry(index_id).index_size = index_size;
+            }
+            // All declared Indexes are kept, unless one was given on command-line
+            let index_ids = match cli.common.index_id {
+                Some(index_id) => vec![index_id],
+                None => config.indexes.iter().map(|x| x.index_id).collect(),
+            };
+            if !attach {
+                ensure_no_dry_run_setup(&config)?;
+            }
+            let keeper_provider = with_provider(rpc_url.clone(), keeper_key()?).await?;
+            let vendor_provider = with_provider(rpc_url.clone(), vendor_key()?).await?;
+            let connect = |private_key| with_provider(rpc_url.clone(), private_key);
+            run(
+                keeper_provider,
+                vendor_provider,
+                config,
+                index_ids,
+                attach,
+                connect,
+            )
+            .await
+        }
+        Command::SetupVendor { market_size } => {
+            if let Some(market_size) = market_size {
+                config.vendor.market_size = market_size;
+            }
+            ensure_no_dry_run_setup(&config)?;
+            let provider = with_provider(rpc_url, vendor_key()?).await?;
+            setup_vendor(provider, config, index_id).await
+        }
+        Command::CreateIndex { index_size } => {
+            if let Some(index_size) = index_size {
+                config.index_entry(index_id).index_size = index_size;
+            }
+            ensure_no_dry_run_setup(&config)?;
+            let provider = with_provider(rpc_url, keeper_key()?).await?;
+            create_index(provider, config, index_id).await
+        }
+        Command::Status { trader, keeper } => {
+            let provider = with_provider(rpc_url, keeper_key()?).await?;
+            status(provider, config, index_id, trader, keeper).await
+        }
+        Command::Claim { side } => {
+            let provider = with_provider(rpc_url, trader_key()?).await?;
+            claim(provider, config, index_id, side).await
+        }
+        Command::Order { side } => {
+            let provider = with_provider(rpc_url, trader_key()?).await?;
+            order(provider, config, index_id, side).await
+        }
+        Command::Ledger {
+            ledger_file,
+            json,
+            export,
+        } => ledger(config, ledger_file, json, export),
+    }
+}
@@ -0,0 +1,208 @@
+use std::{collections::HashMap, path::Path};
+
+use eyre::{Context, OptionExt, bail};
+use itertools::Itertools;
+use serde::Deserialize;
+
+use crate::{
+    common::amount::Amount,
+    market::source::{MarketData, MarketDataSource},
+};
+
+/// Single row of recorded market data
+///
+/// CSV files have header `tick,asset,price,slope,liquidity` (`tick` is
+/// optional), and JSONL files have one such object per line.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarketRecord {
+    #[serde(default)]
+    pub tick: u64,
+    pub asset: u128,
+    pub price: Amount,
+    pub slope: Amount,
+    pub liquidity: Amount,
+}
+
+impl MarketRecord {
+    pub fn market_data(&self) -> MarketData {
+        MarketData {
+            price: self.price,
+            slope: self.slope,
+            liquidity: self.liquidity,
+        }
+    }
+}
+
+pub fn parse_csv(text: &str) -> eyre::Result<Vec<MarketRecord>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let (_, header) = lines.next().ok_or_eyre("Missing CSV header")?;
+    let columns = header.split(',').map(|x| x.trim()).collect_vec();
+
+    let position = |name: &str| columns.iter().position(|x| *x == name);
+    let tick_pos = position("tick");
+    let asset_pos = position("asset").ok_or_eyre("Missing CSV column: asset")?;
+    let price_pos = position("price").ok_or_eyre("Missing CSV column: price")?;
+    let slope_pos = position("slope").ok_or_eyre("Missing CSV column: slope")?;
+    let liquidity_pos = position("liquidity").ok_or_eyre("Missing CSV column: liquidity")?;
+
+    lines
+        .map(|(n, line)| {
+            let fields = line.split(',').map(|x| x.trim()).collect_vec();
+            if fields.len() != columns.len() {
+                bail!("Line {}: expected {} fields", n, columns.len());
+            }
+            let record = MarketRecord {
+                tick: match tick_pos {
+                    Some(pos) => fields[pos].parse()?,
+                    None => 0,
+                },
+                asset: fields[asset_pos].parse()?,
+                price: fields[price_pos].parse()?,
+                slope: fields[slope_pos].parse()?,
+                liquidity: fields[liquidity_pos].parse()?,
+            };
+            Ok(record)
+        })
+        .collect::<eyre::Result<Vec<_>>>()
+}
+
+pub fn parse_jsonl(text: &str) -> eyre::Result<Vec<MarketRecord>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            serde_json::from_str(line).with_context(|| format!("Line {}: invalid record", n + 1))
+        })
+        .collect()
+}
+
+pub fn load_records(path: impl AsRef<Path>) -> eyre::Result<Vec<MarketRecord>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read market data: {}", path.display()))?;
+
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("csv") => parse_csv(&text),
+        Some("jsonl" | "ndjson") => parse_jsonl(&text),
+        _ => bail!("Unsupported market data format: {}", path.display()),
+    }
+    .with_context(|| format!("Failed to parse market data: {}", path.display()))
+}
+
+/// Replays recorded market data, advancing one tick on each update
+///
+/// Assets not present in a tick keep their last replayed values.
+pub struct FileReplay {
+    ticks: Vec<Vec<MarketRecord>>,
+    next_tick: usize,
+    looped: bool,
+    last: HashMap<u128, MarketData>,
+}
+
+impl FileReplay {
+    pub fn new(records: Vec<MarketRecord>, looped: bool) -> eyre::Result<Self> {
+        let ticks = records
+            .into_iter()
+            .sorted_by_key(|x| x.tick)
+            .chunk_by(|x| x.tick)
+            .into_iter()
+            .map(|(_, group)| group.collect_vec())
+            .collect_vec();
+
+        if ticks.is_empty() {
+            bail!("No market data to replay");
+        }
+
+        Ok(Self {
+            ticks,
+            next_tick: 0,
+            looped,
+            last: HashMap::new(),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>, looped: bool) -> eyre::Result<Self> {
+        Self::new(load_records(path)?, looped)
+    }
+
+    fn advance(&mut self) {
+        if self.next_tick == self.ticks.len() {
+            if !self.looped {
+                return;
+            }
+            self.next_tick = 0;
+        }
+
+        for record in &self.ticks[self.next_tick] {
+            self.last.insert(record.asset, record.market_data());
+        }
+
+        self.next_tick += 1;
+    }
+}
+
+impl MarketDataSource for FileReplay {
+    fn market_data(&mut self, assets: &[u128]) -> eyre::Result<Vec<MarketData>> {
+        self.advance();
+
+        assets
+            .iter()
+            .map(|asset| {
+                self.last
+                    .get(asset)
+                    .copied()
+                    .ok_or_else(|| eyre::eyre!("No market data for asset {}", asset))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_replay() {
+        let records = parse_csv(
+            "tick,asset,price,slope,liquidity\n\
+             1,1,100.5,0.01,10\n\
+             1,2,200,0.02,20\n\
+             2,1,101,0.01,10\n",
+        )
+        .unwrap();
+
+        let mut replay = FileReplay::new(records, false).unwrap();
+
+        let first = replay.market_data(&[1, 2]).unwrap();
+        assert_eq!(first[0].price, "100.5".parse().unwrap());
+        assert_eq!(first[1].price, Amount::from_u128_with_scale(200, 0));
+
+        let second = replay.market_data(&[1, 2]).unwrap();
+        assert_eq!(second[0].price, Amount::from_u128_with_scale(101, 0));
+        assert_eq!(second[1].price, Amount::from_u128_with_scale(200, 0));
+
+        let held = replay.market_data(&[1]).unwrap();
+        assert_eq!(held[0].price, Amount::from_u128_with_scale(101, 0));
+
+        assert!(replay.market_data(&[3]).is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl() {
+        let records = parse_jsonl(
+            r#"{"tick": 1, "asset": 7, "price": "1.5", "slope": "0.01", "liquidity": 3}
+            {"asset": 8, "price": 2, "slope": "0.02", "liquidity": "0.5"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].asset, 7);
+        assert_eq!(records[1].tick, 0);
+        assert_eq!(records[1].liquidity, "0.5".parse().unwrap());
+    }
+}
@@ -0,0 +1,34 @@
+use itertools::Itertools;
+
+use crate::{
+    common::rand_value::ValueGen,
+    market::source::{MarketData, MarketDataSource},
+};
+
+#[derive(Default)]
+pub struct RandomMarketData;
+
+impl RandomMarketData {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl MarketDataSource for RandomMarketData {
+    // Values are fixed-point with 2 decimals, grouped at the decimal point
+    #[allow(clippy::inconsistent_digit_grouping, clippy::zero_prefixed_literal)]
+    fn market_data(&mut self, assets: &[u128]) -> eyre::Result<Vec<MarketData>> {
+        let mut price_gen = ValueGen::new(100_00, 1000_00, 2);
+        let mut slope_gen = ValueGen::new(0_01, 0_10, 2);
+        let mut liquidity_gen = ValueGen::new(0_10, 10_00, 2);
+
+        Ok(assets
+            .iter()
+            .map(|_| MarketData {
+                price: price_gen.next(),
+                slope: slope_gen.next(),
+                liquidity: liquidity_gen.next(),
+            })
+            .collect_vec())
+    }
+}
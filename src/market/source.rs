@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::amount::Amount,
+    market::{file_replay::FileReplay, random::RandomMarketData, static_file::StaticFile},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketData {
+    pub price: Amount,
+    pub slope: Amount,
+    pub liquidity: Amount,
+}
+
+/// Source of market data submitted by Vendor via `IBanker::submitMarketData`
+pub trait MarketDataSource: Send {
+    /// Market data for each of the assets, in the same order as the assets
+    fn market_data(&mut self, assets: &[u128]) -> eyre::Result<Vec<MarketData>>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MarketDataConfig {
+    /// Random prices, slopes and liquidity
+    #[default]
+    Random,
+    /// Replay recorded ticks from CSV or JSONL file, one tick per update
+    Replay {
+        path: PathBuf,
+        /// Start over once all ticks were replayed, otherwise hold last tick
+        #[serde(default)]
+        looped: bool,
+    },
+    /// Fixed market data from CSV or JSONL file
+    Static { path: PathBuf },
+}
+
+impl MarketDataConfig {
+    pub fn build(&self) -> eyre::Result<Box<dyn MarketDataSource>> {
+        let source: Box<dyn MarketDataSource> = match self {
+            MarketDataConfig::Random => Box::new(RandomMarketData::new()),
+            MarketDataConfig::Replay { path, looped } => Box::new(FileReplay::load(path, *looped)?),
+            MarketDataConfig::Static { path } => Box::new(StaticFile::load(path)?),
+        };
+        Ok(source)
+    }
+}
@@ -0,0 +1,42 @@
+use std::{collections::HashMap, path::Path};
+
+use eyre::bail;
+
+use crate::market::{
+    file_replay::load_records,
+    source::{MarketData, MarketDataSource},
+};
+
+/// Fixed market data loaded once from CSV or JSONL file (ticks are ignored)
+pub struct StaticFile {
+    data: HashMap<u128, MarketData>,
+}
+
+impl StaticFile {
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let data: HashMap<_, _> = load_records(path)?
+            .into_iter()
+            .map(|x| (x.asset, x.market_data()))
+            .collect();
+
+        if data.is_empty() {
+            bail!("No market data in static file");
+        }
+
+        Ok(Self { data })
+    }
+}
+
+impl MarketDataSource for StaticFile {
+    fn market_data(&mut self, assets: &[u128]) -> eyre::Result<Vec<MarketData>> {
+        assets
+            .iter()
+            .map(|asset| {
+                self.data
+                    .get(asset)
+                    .copied()
+                    .ok_or_else(|| eyre::eyre!("No market data for asset {}", asset))
+            })
+            .collect()
+    }
+}
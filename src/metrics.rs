@@ -0,0 +1,314 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, atomic::AtomicU64},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::Address,
+    providers::{Provider, WalletProvider},
+};
+use axum::{Router, extract::State, http::header, response::IntoResponse, routing::get};
+use eyre::Context;
+use prometheus_client::{
+    encoding::{EncodeLabelSet, text::encode},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::Registry,
+};
+use tokio::time::{MissedTickBehavior, interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct MessageLabels {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct OperationLabels {
+    pub operation: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct OutcomeLabels {
+    pub operation: String,
+    /// One of `mined`, `reverted`, `failed` or `simulated`
+    pub outcome: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct WalletLabels {
+    pub wallet: String,
+}
+
+type HistogramFamily<L> = Family<L, Histogram, fn() -> Histogram>;
+
+fn latency_histogram() -> Histogram {
+    // From 50ms to about 7 minutes
+    Histogram::new(exponential_buckets(0.05, 2.0, 14))
+}
+
+/// Metrics of the daemon, exposed in Prometheus format on `/metrics`
+///
+/// Cloning is cheap, and all clones update the same metrics.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Arc<Registry>,
+    chain_messages: Family<MessageLabels, Counter>,
+    transactions: Family<OutcomeLabels, Counter>,
+    gas_used: Family<OperationLabels, Counter>,
+    event_latency: HistogramFamily<MessageLabels>,
+    handler_duration: HistogramFamily<MessageLabels>,
+    queue_depth: Gauge,
+    gas_balance: Family<WalletLabels, Gauge<f64, AtomicU64>>,
+    last_block: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::with_prefix("conveyor");
+
+        let chain_messages = Family::<MessageLabels, Counter>::default();
+        registry.register(
+            "chain_messages",
+            "Chain messages received, per variant",
+            chain_messages.clone(),
+        );
+
+        let transactions = Family::<OutcomeLabels, Counter>::default();
+        registry.register(
+            "transactions",
+            "Transactions sent, per operation and outcome",
+            transactions.clone(),
+        );
+
+        let gas_used = Family::<OperationLabels, Counter>::default();
+        registry.register(
+            "gas_used",
+            "Gas used by mined transactions, per operation",
+            gas_used.clone(),
+        );
+
+        let event_latency: HistogramFamily<MessageLabels> =
+            Family::new_with_constructor(latency_histogram);
+        registry.register(
+            "event_latency_seconds",
+            "Time from block of the event to its transactions being mined",
+            event_latency.clone(),
+        );
+
+        let handler_duration: HistogramFamily<MessageLabels> =
+            Family::new_with_constructor(latency_histogram);
+        registry.register(
+            "event_handler_seconds",
+            "Time it took to handle event, including retries",
+            handler_duration.clone(),
+        );
+
+        let queue_depth = Gauge::default();
+        registry.register(
+            "queue_depth",
+            "Events waiting in the channel between pulley and app",
+            queue_depth.clone(),
+        );
+
+        let gas_balance = Family::<WalletLabels, Gauge<f64, AtomicU64>>::default();
+        registry.register(
+            "gas_balance_eth",
+            "Balance of the wallet paying for gas",
+            gas_balance.clone(),
+        );
+
+        let last_block = Gauge::default();
+        registry.register(
+            "last_processed_block",
+            "Block of the last processed event",
+            last_block.clone(),
+        );
+
+        Self {
+            registry: Arc::new(registry),
+            chain_messages,
+            transactions,
+            gas_used,
+            event_latency,
+            handler_duration,
+            queue_depth,
+            gas_balance,
+            last_block,
+        }
+    }
+
+    pub fn inc_chain_message(&self, message: &str) {
+        self.chain_messages
+            .get_or_create(&MessageLabels {
+                message: message.to_owned(),
+            })
+            .inc();
+    }
+
+    pub fn inc_transaction(&self, operation: &str, outcome: &'static str) {
+        self.transactions
+            .get_or_create(&OutcomeLabels {
+                operation: operation.to_owned(),
+                outcome,
+            })
+            .inc();
+    }
+
+    pub fn inc_gas_used(&self, operation: &str, gas_used: u64) {
+        self.gas_used
+            .get_or_create(&OperationLabels {
+                operation: operation.to_owned(),
+            })
+            .inc_by(gas_used);
+    }
+
+    pub fn observe_event_latency(&self, message: &str, latency: Duration) {
+        self.event_latency
+            .get_or_create(&MessageLabels {
+                message: message.to_owned(),
+            })
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn observe_handler_duration(&self, message: &str, duration: Duration) {
+        self.handler_duration
+            .get_or_create(&MessageLabels {
+                message: message.to_owned(),
+            })
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.set(depth as i64);
+    }
+
+    pub fn set_gas_balance(&self, wallet: Address, balance_eth: f64) {
+        self.gas_balance
+            .get_or_create(&WalletLabels {
+                wallet: wallet.to_string(),
+            })
+            .set(balance_eth);
+    }
+
+    pub fn set_last_block(&self, block_number: u64) {
+        self.last_block.set(block_number as i64);
+    }
+
+    /// Metrics in Prometheus text format
+    pub fn encode(&self) -> eyre::Result<String> {
+        let mut output = String::new();
+        encode(&mut output, &self.registry).context("Failed to encode metrics")?;
+        Ok(output)
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn metrics_handler(State(metrics): State<Metrics>) -> impl IntoResponse {
+    match metrics.encode() {
+        Ok(body) => (
+            [(
+                header::CONTENT_TYPE,
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )],
+            body,
+        )
+            .into_response(),
+        Err(err) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{:?}", err),
+        )
+            .into_response(),
+    }
+}
+
+/// Routes serving metrics, to be merged into HTTP server
+pub fn router(metrics: Metrics) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics)
+}
+
+/// Serve routes until cancelled
+pub async fn serve(
+    listen: SocketAddr,
+    router: Router,
+    cancel: CancellationToken,
+) -> eyre::Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+
+    info!(%listen, "📊 HTTP server started");
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move { cancel.cancelled().await })
+        .await
+        .context("HTTP server failed")
+}
+
+/// Update gas balance of the wallet periodically, until cancelled
+pub async fn poll_gas_balance<P>(
+    provider: P,
+    metrics: Metrics,
+    period: Duration,
+    cancel: CancellationToken,
+) where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    let wallet = provider.default_signer_address();
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = interval.tick() => {
+                match provider.get_balance(wallet).await {
+                    Ok(balance) => {
+                        let balance = u128::try_from(balance).unwrap_or(u128::MAX);
+                        metrics.set_gas_balance(wallet, balance as f64 / 1e18);
+                    }
+                    Err(err) => warn!(%wallet, "Failed to obtain gas balance: {:?}", err),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_metrics_encode() {
+        let metrics = Metrics::new();
+        metrics.inc_chain_message("BuyOrder");
+        metrics.inc_transaction("submitSupply", "mined");
+        metrics.inc_gas_used("submitSupply", 21_000);
+        metrics.observe_event_latency("BuyOrder", Duration::from_secs(3));
+        metrics.observe_handler_duration("BuyOrder", Duration::from_millis(300));
+        metrics.set_last_block(1234);
+
+        let output = metrics.encode().unwrap();
+        assert!(output.contains(r#"conveyor_chain_messages_total{message="BuyOrder"} 1"#));
+        assert!(output.contains(
+            r#"conveyor_transactions_total{operation="submitSupply",outcome="mined"} 1"#
+        ));
+        assert!(output.contains(r#"conveyor_gas_used_total{operation="submitSupply"} 21000"#));
+        assert!(output.contains("conveyor_last_processed_block 1234"));
+        assert!(output.contains(r#"conveyor_event_latency_seconds_count{message="BuyOrder"} 1"#));
+        assert!(output.contains(r#"conveyor_event_handler_seconds_count{message="BuyOrder"} 1"#));
+    }
+}
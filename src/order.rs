@@ -0,0 +1,221 @@
+use std::fmt::Display;
+
+use alloy::primitives::Address;
+use eyre::{OptionExt, eyre};
+use serde::Serialize;
+
+use crate::common::{
+    amount::Amount,
+    constants::{
+        ORDER_BURNED_OFFSET, ORDER_COLLATERAL_OFFSET, ORDER_LOCKED_OFFSET, ORDER_MINTED_OFFSET,
+        ORDER_SPENT_OFFSET, ORDER_WITHDRAW_OFFSET,
+    },
+    vector::Vector,
+};
+
+/// Number of amounts in encoded order
+pub const ORDER_LEN: usize = ORDER_WITHDRAW_OFFSET + 1;
+
+/// Amounts of an order, as encoded by Steward
+///
+/// Same layout is used for orders of single trader, of single vendor, and
+/// for the total order of the Index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Order {
+    /// Collateral remaining to be spent on buying ITP
+    pub collateral: Amount,
+    /// Collateral already spent
+    pub spent: Amount,
+    /// ITP minted from spent collateral
+    pub minted: Amount,
+    /// ITP remaining to be sold
+    pub locked: Amount,
+    /// ITP already sold and burned
+    pub burned: Amount,
+    /// Collateral obtained from burned ITP
+    pub withdraw: Amount,
+}
+
+impl Order {
+    pub const ZERO: Order = Order {
+        collateral: Amount::ZERO,
+        spent: Amount::ZERO,
+        minted: Amount::ZERO,
+        locked: Amount::ZERO,
+        burned: Amount::ZERO,
+        withdraw: Amount::ZERO,
+    };
+
+    /// Decode order from bytes returned by Steward
+    ///
+    /// No order at all is returned as empty bytes, and is decoded as zero order.
+    pub fn try_from_bytes(data: impl AsRef<[u8]>) -> eyre::Result<Self> {
+        let data = data.as_ref();
+        if data.is_empty() {
+            return Ok(Self::ZERO);
+        }
+        let len = Vector::len_from_vec(&data)
+            .ok_or_eyre(format!("Invalid order data length: {} bytes", data.len()))?;
+        if len < ORDER_LEN {
+            return Err(eyre!(
+                "Invalid order: expected {} amounts, got {}",
+                ORDER_LEN,
+                len
+            ));
+        }
+        let order = Vector::from_vec(data);
+        Ok(Self {
+            collateral: order.data[ORDER_COLLATERAL_OFFSET],
+            spent: order.data[ORDER_SPENT_OFFSET],
+            minted: order.data[ORDER_MINTED_OFFSET],
+            locked: order.data[ORDER_LOCKED_OFFSET],
+            burned: order.data[ORDER_BURNED_OFFSET],
+            withdraw: order.data[ORDER_WITHDRAW_OFFSET],
+        })
+    }
+
+    /// Collateral remaining to be spent
+    pub fn remaining_collateral(&self) -> Amount {
+        self.collateral
+    }
+
+    /// ITP locked for selling, and not yet burned
+    pub fn pending_itp(&self) -> Amount {
+        self.locked
+    }
+
+    /// Collateral deposited for buying, spent or not
+    pub fn total_collateral(&self) -> Option<Amount> {
+        self.collateral.checked_add(self.spent)
+    }
+
+    /// ITP locked for selling, burned or not
+    pub fn total_locked(&self) -> Option<Amount> {
+        self.locked.checked_add(self.burned)
+    }
+
+    /// Whether remaining collateral is worth processing Buy order
+    pub fn has_pending_buy(&self, min_remain: u128) -> bool {
+        min_remain < self.collateral.to_u128_raw()
+    }
+
+    /// Whether remaining locked ITP is worth processing Sell order
+    pub fn has_pending_sell(&self, min_remain: u128) -> bool {
+        min_remain < self.locked.to_u128_raw()
+    }
+}
+
+impl Display for Order {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "collateral={} spent={} minted={} locked={} burned={} withdraw={}",
+            self.collateral, self.spent, self.minted, self.locked, self.burned, self.withdraw
+        )
+    }
+}
+
+/// Order of a trader, from `ISteward::getTraderOrder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TraderOrder {
+    pub trader: Address,
+    pub order: Order,
+}
+
+impl TraderOrder {
+    pub fn try_from_bytes(trader: Address, data: impl AsRef<[u8]>) -> eyre::Result<Self> {
+        let order = Order::try_from_bytes(data)
+            .map_err(|err| err.wrap_err(format!("Failed to decode order of trader {}", trader)))?;
+        Ok(Self { trader, order })
+    }
+}
+
+impl Display for TraderOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trader={} {}", self.trader, self.order)
+    }
+}
+
+/// Order of a vendor, from `ISteward::getVendorOrder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VendorOrder {
+    pub vendor_id: u128,
+    pub order: Order,
+}
+
+impl VendorOrder {
+    pub fn try_from_bytes(vendor_id: u128, data: impl AsRef<[u8]>) -> eyre::Result<Self> {
+        let order = Order::try_from_bytes(data).map_err(|err| {
+            err.wrap_err(format!("Failed to decode order of vendor {}", vendor_id))
+        })?;
+        Ok(Self { vendor_id, order })
+    }
+}
+
+impl Display for VendorOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "vendor_id={} {}", self.vendor_id, self.order)
+    }
+}
+
+/// Total order of the Index, from `ISteward::getTotalOrder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TotalOrder {
+    pub index_id: u128,
+    pub order: Order,
+}
+
+impl TotalOrder {
+    pub fn try_from_bytes(index_id: u128, data: impl AsRef<[u8]>) -> eyre::Result<Self> {
+        let order = Order::try_from_bytes(data).map_err(|err| {
+            err.wrap_err(format!(
+                "Failed to decode total order of index {}",
+                index_id
+            ))
+        })?;
+        Ok(Self { index_id, order })
+    }
+}
+
+impl Display for TotalOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "index_id={} {}", self.index_id, self.order)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_order_from_bytes() {
+        let amounts = Vector::from_vec_u128(
+            [100, 400, 20, 5, 15, 300]
+                .map(|x| x * Amount::SCALE)
+                .to_vec(),
+        );
+        let order = Order::try_from_bytes(amounts.to_vec()).unwrap();
+        assert_eq!(
+            order.remaining_collateral(),
+            Amount::from_u128_raw(100 * Amount::SCALE)
+        );
+        assert_eq!(
+            order.pending_itp(),
+            Amount::from_u128_raw(5 * Amount::SCALE)
+        );
+        assert_eq!(
+            order.total_collateral(),
+            Some(Amount::from_u128_raw(500 * Amount::SCALE))
+        );
+        assert!(order.has_pending_buy(100));
+        assert!(!order.has_pending_sell(10 * Amount::SCALE));
+
+        assert_eq!(Order::try_from_bytes([]).unwrap(), Order::ZERO);
+
+        let short = Vector::from_vec_u128(vec![1, 2, 3]);
+        let err = TraderOrder::try_from_bytes(Address::ZERO, short.to_vec()).unwrap_err();
+        assert!(format!("{:#}", err).contains("expected 6 amounts, got 3"));
+
+        assert!(Order::try_from_bytes([0u8; 17]).is_err());
+    }
+}
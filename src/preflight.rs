@@ -0,0 +1,295 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use alloy::{primitives::Address, providers::Provider};
+use eyre::Context;
+
+use crate::{
+    common::amount::Amount,
+    roles::{Role, RoleCheck, RoleIds},
+};
+
+/// Contract, which Conveyor calls or refers to
+#[derive(Debug, Clone)]
+pub struct ContractCheck {
+    pub name: String,
+    pub address: Address,
+    pub has_code: bool,
+    /// Custody may be an account without code, so only its address is checked
+    pub require_code: bool,
+}
+
+/// Gas balance of a signer, compared to minimum required to start
+#[derive(Debug, Clone)]
+pub struct GasCheck {
+    pub address: Address,
+    pub balance: Amount,
+    pub min_balance: Amount,
+}
+
+/// Signer, and roles it needs
+#[derive(Debug, Clone, Default)]
+pub struct SignerRoles {
+    pub required: Vec<Role>,
+    /// Roles neither required nor reported as excess
+    pub optional: Vec<Role>,
+}
+
+impl SignerRoles {
+    /// Signer of one-shot command, which may share its key with the daemon and hold its roles
+    pub fn one_shot(required: Vec<Role>) -> Self {
+        let optional = Role::ALL
+            .into_iter()
+            .filter(|x| !required.contains(x))
+            .collect();
+        Self { required, optional }
+    }
+}
+
+/// Checks run before setup, so that missing roles, gas or contracts are
+/// reported up front instead of as reverts halfway through setup
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    pub contracts: Vec<ContractCheck>,
+    pub roles: Vec<RoleCheck>,
+    pub gas: Vec<GasCheck>,
+}
+
+impl PreflightReport {
+    pub async fn fetch<P: Provider>(
+        provider: &P,
+        castle_address: Address,
+        contracts: BTreeMap<Address, String>,
+        addresses: BTreeMap<Address, String>,
+        signers: BTreeMap<Address, SignerRoles>,
+        min_balance: Amount,
+    ) -> eyre::Result<Self> {
+        let mut report = Self::default();
+
+        for (address, name) in contracts {
+            let has_code = if address.is_zero() {
+                false
+            } else {
+                !provider
+                    .get_code_at(address)
+                    .await
+                    .with_context(|| format!("Failed to obtain code of {} {}", name, address))?
+                    .is_empty()
+            };
+            report.contracts.push(ContractCheck {
+                name,
+                address,
+                has_code,
+                require_code: true,
+            });
+        }
+
+        for (address, name) in addresses {
+            report.contracts.push(ContractCheck {
+                name,
+                address,
+                has_code: false,
+                require_code: false,
+            });
+        }
+
+        // Roles can only be resolved when Castle is there
+        let has_castle = report
+            .contracts
+            .iter()
+            .any(|x| x.address == castle_address && x.has_code);
+
+        if has_castle {
+            let role_ids = RoleIds::fetch(provider, castle_address).await?;
+            for (address, roles) in &signers {
+                report.roles.push(
+                    RoleCheck::fetch(
+                        provider,
+                        castle_address,
+                        &role_ids,
+                        *address,
+                        roles.required.clone(),
+                        roles.optional.clone(),
+                    )
+                    .await?,
+                );
+            }
+        }
+
+        for address in signers.keys() {
+            let balance = provider
+                .get_balance(*address)
+                .await
+                .with_context(|| format!("Failed to obtain gas balance of {}", address))?;
+            report.gas.push(GasCheck {
+                address: *address,
+                balance: Amount::try_from_u256(balance).unwrap_or(Amount::MAX),
+                min_balance,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// What needs to be done before Conveyor can start
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for contract in &self.contracts {
+            if contract.address.is_zero() {
+                problems.push(format!(
+                    "{} address is not set, configure it in [network] or [[indexes]]",
+                    contract.name
+                ));
+            } else if contract.require_code && !contract.has_code {
+                problems.push(format!(
+                    "{} {} has no contract code, check the address and RPC_URL",
+                    contract.name, contract.address
+                ));
+            }
+        }
+
+        for check in &self.roles {
+            for role in check.missing() {
+                problems.push(format!(
+                    "{} is missing {}, run: ./scripts/roles.sh grant $CASTLE \"Castle.{}\" {}",
+                    check.address, role, role, check.address
+                ));
+            }
+            for role in check.excess() {
+                problems.push(format!(
+                    "{} doesn't need {}, run: ./scripts/roles.sh revoke $CASTLE \"Castle.{}\" {}",
+                    check.address, role, role, check.address
+                ));
+            }
+        }
+
+        for check in &self.gas {
+            if check.balance.is_less_than(&check.min_balance) {
+                problems.push(format!(
+                    "{} has {} of gas token, fund it with at least {}",
+                    check.address, check.balance, check.min_balance
+                ));
+            }
+        }
+
+        problems
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+impl Display for PreflightReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for contract in &self.contracts {
+            let status = if contract.address.is_zero() {
+                "not set"
+            } else if contract.has_code || !contract.require_code {
+                "ok"
+            } else {
+                "no code"
+            };
+            writeln!(f, "  {} {}: {}", contract.name, contract.address, status)?;
+        }
+        for check in &self.roles {
+            writeln!(f, "  {}", check)?;
+        }
+        for check in &self.gas {
+            writeln!(f, "  {} gas balance: {}", check.address, check.balance)?;
+        }
+        let problems = self.problems();
+        if !problems.is_empty() {
+            writeln!(f, "To fix:")?;
+            for problem in problems {
+                writeln!(f, "  - {}", problem)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn test_preflight_problems() {
+        let signer = address!("0x1000000000000000000000000000000000000001");
+        let report = PreflightReport {
+            contracts: vec![
+                ContractCheck {
+                    name: "Castle".into(),
+                    address: address!("0x2000000000000000000000000000000000000002"),
+                    has_code: true,
+                    require_code: true,
+                },
+                ContractCheck {
+                    name: "Custody".into(),
+                    address: Address::ZERO,
+                    has_code: false,
+                    require_code: false,
+                },
+                ContractCheck {
+                    name: "Custody of Index 2".into(),
+                    address: address!("0x3000000000000000000000000000000000000003"),
+                    has_code: false,
+                    require_code: false,
+                },
+            ],
+            roles: vec![RoleCheck {
+                address: signer,
+                required: vec![Role::Keeper],
+                optional: vec![],
+                granted: vec![Role::Keeper],
+            }],
+            gas: vec![GasCheck {
+                address: signer,
+                balance: "0.05".parse().unwrap(),
+                min_balance: "0.1".parse().unwrap(),
+            }],
+        };
+
+        let problems = report.problems();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("Custody address is not set"));
+        assert!(problems[1].contains("fund it with at least"));
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn test_preflight_single_key() {
+        // Single PRIVATE_KEY holds all roles, while setup-vendor only needs VENDOR_ROLE
+        let signer = address!("0x1000000000000000000000000000000000000001");
+        let roles = SignerRoles::one_shot(vec![Role::Vendor]);
+        assert_eq!(roles.optional, vec![Role::Issuer, Role::Keeper]);
+
+        let report = PreflightReport {
+            contracts: vec![],
+            roles: vec![RoleCheck {
+                address: signer,
+                required: roles.required,
+                optional: roles.optional,
+                granted: Role::ALL.to_vec(),
+            }],
+            gas: vec![],
+        };
+        assert!(report.is_ok());
+
+        let roles = SignerRoles::one_shot(vec![Role::Keeper, Role::Issuer]);
+        let report = PreflightReport {
+            contracts: vec![],
+            roles: vec![RoleCheck {
+                address: signer,
+                required: roles.required,
+                optional: roles.optional,
+                granted: vec![Role::Issuer, Role::Vendor],
+            }],
+            gas: vec![],
+        };
+        assert_eq!(report.problems().len(), 1);
+        assert!(report.problems()[0].contains("is missing KEEPER_ROLE"));
+    }
+}
@@ -1,20 +1,34 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    pin::Pin,
+};
+
 use alloy::{
-    primitives::Address,
+    eips::BlockNumberOrTag,
+    primitives::{Address, B256},
     providers::{Provider, WalletProvider},
-    rpc::types::Filter,
+    rpc::types::{Filter, Log},
 };
 use alloy_sol_types::SolEvent;
 use eyre::Context;
-use futures_util::StreamExt;
-use tokio::sync::mpsc::UnboundedSender;
+use futures_util::{Stream, StreamExt};
+use tokio::{
+    sync::mpsc::UnboundedSender,
+    time::{Instant, MissedTickBehavior, interval, sleep, sleep_until},
+};
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{debug, info, warn};
 
-use crate::interfaces::{
-    vault_native_claims::IVaultNativeClaims, vault_native_orders::IVaultNativeOrders,
+use crate::{
+    checkpoint::CheckpointHandle,
+    config::PulleyConfig,
+    confirmations::{Confirmations, Pushed},
+    interfaces::{
+        vault_native_claims::IVaultNativeClaims, vault_native_orders::IVaultNativeOrders,
+    },
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ChainMessage {
     BuyOrder {
         keeper: Address,
@@ -62,23 +76,164 @@ pub enum ChainMessage {
         itp_remain: u128,
         itp_burned: u128,
     },
+    /// Event from this block was already forwarded, but block is no longer
+    /// canonical, so state derived from it must be re-synced
+    Reorg { block_number: u64 },
+}
+
+impl ChainMessage {
+    /// Name of the variant, e.g. for metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainMessage::BuyOrder { .. } => "BuyOrder",
+            ChainMessage::SellOrder { .. } => "SellOrder",
+            ChainMessage::Acquisition { .. } => "Acquisition",
+            ChainMessage::Disposal { .. } => "Disposal",
+            ChainMessage::AcquisitionClaim { .. } => "AcquisitionClaim",
+            ChainMessage::DisposalClaim { .. } => "DisposalClaim",
+            ChainMessage::Reorg { .. } => "Reorg",
+        }
+    }
+}
+
+/// Where on chain the event was emitted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventMeta {
+    pub block_number: Option<u64>,
+    pub block_hash: Option<B256>,
+    pub tx_hash: Option<B256>,
+    pub log_index: Option<u64>,
+    pub timestamp: Option<u64>,
+}
+
+impl EventMeta {
+    pub fn from_log(log: &Log) -> Self {
+        Self {
+            block_number: log.block_number,
+            block_hash: log.block_hash,
+            tx_hash: log.transaction_hash,
+            log_index: log.log_index,
+            timestamp: log.block_timestamp,
+        }
+    }
 }
 
-pub struct Pulley;
+impl core::fmt::Display for EventMeta {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.block_number {
+            Some(block_number) => write!(f, "#{}", block_number)?,
+            None => write!(f, "#pending")?,
+        }
+        if let Some(tx_hash) = self.tx_hash {
+            write!(f, " tx={}", tx_hash)?;
+        }
+        if let Some(log_index) = self.log_index {
+            write!(f, " log={}", log_index)?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " ts={}", timestamp)?;
+        }
+        Ok(())
+    }
+}
+
+/// Chain message together with metadata of the log it was decoded from
+#[derive(Debug, Clone)]
+pub struct ChainEvent {
+    pub meta: EventMeta,
+    pub message: ChainMessage,
+}
 
-impl Pulley {
-    pub async fn run<P>(
+/// Recently forwarded logs, so that logs delivered again are dropped
+struct SeenLogs {
+    capacity: usize,
+    order: VecDeque<LogKey>,
+    seen: HashSet<LogKey>,
+}
+
+/// Block hash, transaction hash and index of the log
+type LogKey = (Option<B256>, Option<B256>, u64);
+
+impl SeenLogs {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Remember log, returning false if it was seen before
+    ///
+    /// Logs are identified by block hash, so that log included again in
+    /// another block after reorg is not considered duplicate.
+    fn insert(&mut self, meta: &EventMeta) -> bool {
+        let Some(log_index) = meta.log_index else {
+            return true;
+        };
+        if meta.block_hash.is_none() && meta.tx_hash.is_none() {
+            return true;
+        }
+
+        let key = (meta.block_hash, meta.tx_hash, log_index);
+        if !self.seen.insert(key) {
+            return false;
+        }
+
+        self.order.push_back(key);
+        while self.capacity < self.order.len() {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        true
+    }
+}
+
+/// Why forwarding of logs has stopped
+enum StreamEnd {
+    Cancelled,
+    Interrupted,
+}
+
+type LogStream = Pin<Box<dyn Stream<Item = Log> + Send>>;
+
+const SEEN_LOGS_CAPACITY: usize = 10_000;
+const BLOCK_TIMESTAMPS_CAPACITY: usize = 256;
+
+pub struct Pulley<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    provider: P,
+    filter: Filter,
+    config: PulleyConfig,
+    checkpoint: CheckpointHandle,
+    confirmations: Confirmations,
+    seen_logs: SeenLogs,
+    /// Last log processed before restart, in block being backfilled again
+    resume_after: Option<(u64, u64)>,
+    block_timestamps: BTreeMap<u64, u64>,
+    sender: UnboundedSender<ChainEvent>,
+    cancel: CancellationToken,
+}
+
+impl<P> Pulley<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    pub async fn run(
         provider: P,
-        vault_address: Address,
-        sender: UnboundedSender<ChainMessage>,
+        vault_addresses: Vec<Address>,
+        config: PulleyConfig,
+        checkpoint: CheckpointHandle,
+        sender: UnboundedSender<ChainEvent>,
         cancel: CancellationToken,
-    ) -> eyre::Result<()>
-    where
-        P: Provider + WalletProvider + Clone + 'static,
-    {
+    ) -> eyre::Result<()> {
         info!("🏎️  Pulley loop started...");
 
-        let filter = Filter::new().address(vault_address).events(vec![
+        let filter = Filter::new().address(vault_addresses).events(vec![
             IVaultNativeOrders::BuyOrder::SIGNATURE,
             IVaultNativeOrders::SellOrder::SIGNATURE,
             IVaultNativeOrders::Acquisition::SIGNATURE,
@@ -87,99 +242,491 @@ impl Pulley {
             IVaultNativeClaims::DisposalClaim::SIGNATURE,
         ]);
 
-        let mut stream = provider.watch_logs(&filter).await?.into_stream();
+        let confirmations = Confirmations::new(config.confirmations);
+        let resume_after = checkpoint.last_log();
+
+        let mut pulley = Self {
+            provider,
+            filter,
+            config,
+            checkpoint,
+            confirmations,
+            seen_logs: SeenLogs::new(SEEN_LOGS_CAPACITY),
+            resume_after,
+            block_timestamps: BTreeMap::new(),
+            sender,
+            cancel,
+        };
+
+        pulley.run_loop().await
+    }
 
+    async fn run_loop(&mut self) -> eyre::Result<()> {
         loop {
-            tokio::select! {
-                _ = cancel.cancelled() => {
+            // Live stream is established before backfill, so that no logs are
+            // missed in between, and logs already backfilled are skipped.
+            let end = match self.live_logs().await {
+                Some((stream, until)) => match self.backfill().await? {
+                    Some(end) => end,
+                    None => self.forward_logs(stream, until).await?,
+                },
+                None => StreamEnd::Interrupted,
+            };
+
+            match end {
+                StreamEnd::Cancelled => {
                     info!("Pulley loop complete.");
-                    return Ok(())
+                    return Ok(());
                 }
-                Some(logs) = stream.next() => {
-                    for log in logs {
-                        if let Ok(event) = log.log_decode::<IVaultNativeOrders::BuyOrder>() {
-                            let event = event.data();
-                            sender
-                                .send(ChainMessage::BuyOrder {
-                                    keeper: event.keeper,
-                                    trader: event.trader,
-                                    index_id: event.index_id,
-                                    vendor_id: event.vendor_id,
-                                    collateral: event.collateral_amount,
-                                })
-                                .context("Failed to send chain event")?;
+                StreamEnd::Interrupted => {
+                    warn!("Log stream interrupted, reconnecting...");
+                    tokio::select! {
+                        _ = self.cancel.cancelled() => {
+                            info!("Pulley loop complete.");
+                            return Ok(())
                         }
+                        _ = sleep(self.config.reconnect_delay()) => {}
+                    }
+                }
+            }
+        }
+    }
 
-                        if let Ok(event) = log.log_decode::<IVaultNativeOrders::SellOrder>() {
-                            let event = event.data();
-                            sender
-                                .send(ChainMessage::SellOrder {
-                                    keeper: event.keeper,
-                                    trader: event.trader,
-                                    index_id: event.index_id,
-                                    vendor_id: event.vendor_id,
-                                    itp_amount: event.itp_amount,
-                                })
-                                .context("Failed to send chain event")?;
-                        }
+    /// Subscribe to logs, or install log filter if we cannot subscribe
+    ///
+    /// When polling only because subscription failed, also returns the time
+    /// to try subscribing again.
+    async fn live_logs(&self) -> Option<(LogStream, Option<Instant>)> {
+        let mut until = None;
 
-                        if let Ok(event) = log.log_decode::<IVaultNativeOrders::Acquisition>() {
-                            let event = event.data();
-                            sender
-                                .send(ChainMessage::Acquisition {
-                                    controller: event.controller,
-                                    index_id: event.index_id,
-                                    vendor_id: event.vendor_id,
-                                    remain: event.remain,
-                                    spent: event.spent,
-                                    minted: event.itp_minted,
-                                })
-                                .context("Failed to send chain event")?;
-                        }
+        // Only pub-sub transports (ws://, ipc) can subscribe, otherwise we poll filters
+        if self.provider.client().pubsub_frontend().is_some() {
+            match self.provider.subscribe_logs(&self.filter).await {
+                Ok(subscription) => {
+                    info!("📡 Subscribed to logs");
+                    return Some((Box::pin(subscription.into_stream()), None));
+                }
+                Err(err) => {
+                    warn!(
+                        "Failed to subscribe to logs, falling back to polling: {:?}",
+                        err
+                    );
+                    until = Some(Instant::now() + self.config.resubscribe_after());
+                }
+            }
+        }
+
+        let mut poller = match self.provider.watch_logs(&self.filter).await {
+            Ok(poller) => poller,
+            Err(err) => {
+                warn!("Failed to install log filter: {:?}", err);
+                return None;
+            }
+        };
+
+        if let Some(poll_interval) = self.config.poll_interval() {
+            poller = poller.with_poll_interval(poll_interval);
+        }
+
+        info!("🔁 Polling logs");
+        let stream = poller.into_stream().flat_map(futures_util::stream::iter);
+
+        Some((Box::pin(stream), until))
+    }
+
+    /// Fetch logs emitted after checkpoint up to current head in bounded
+    /// block ranges
+    async fn backfill(&mut self) -> eyre::Result<Option<StreamEnd>> {
+        let head = match self.provider.get_block_number().await {
+            Ok(head) => head,
+            Err(err) => {
+                warn!("Failed to obtain block number: {:?}", err);
+                return Ok(Some(StreamEnd::Interrupted));
+            }
+        };
+
+        let Some(last_block) = self.checkpoint.last_forwarded() else {
+            // Nothing was processed before, so there is nothing to catch up with
+            self.checkpoint.forwarded(head)?;
+            return Ok(None);
+        };
+
+        let mut from_block = last_block + 1;
+        if head < from_block {
+            return Ok(None);
+        }
+
+        info!(%from_block, to_block = %head, "⏪ Backfilling logs...");
+
+        while from_block <= head {
+            if self.cancel.is_cancelled() {
+                return Ok(Some(StreamEnd::Cancelled));
+            }
+
+            let to_block = head.min(from_block + self.config.backfill_range.max(1) - 1);
+            let range_filter = self
+                .filter
+                .clone()
+                .from_block(from_block)
+                .to_block(to_block);
+
+            let logs = match self.provider.get_logs(&range_filter).await {
+                Ok(logs) => logs,
+                Err(err) => {
+                    warn!(%from_block, %to_block, "Failed to backfill logs: {:?}", err);
+                    return Ok(Some(StreamEnd::Interrupted));
+                }
+            };
+
+            debug!(%from_block, %to_block, count = %logs.len(), "Backfilled logs");
+
+            for log in logs {
+                self.accept(log)?;
+            }
+
+            if !self.release(head).await? {
+                return Ok(Some(StreamEnd::Interrupted));
+            }
+
+            // Logs from unconfirmed blocks are still pending, and must be
+            // fetched again if we restart before they are released
+            let safe_block = self.confirmations.safe_block(head);
+            self.checkpoint.forwarded(to_block.min(safe_block))?;
+            from_block = to_block + 1;
+        }
 
-                        if let Ok(event) = log.log_decode::<IVaultNativeOrders::Disposal>() {
-                            let event = event.data();
-                            sender
-                                .send(ChainMessage::Disposal {
-                                    controller: event.controller,
-                                    index_id: event.index_id,
-                                    vendor_id: event.vendor_id,
-                                    remain: event.itp_remain,
-                                    burned: event.itp_burned,
-                                    gains: event.gains,
-                                })
-                                .context("Failed to send chain event")?;
+        Ok(None)
+    }
+
+    async fn forward_logs(
+        &mut self,
+        mut stream: LogStream,
+        until: Option<Instant>,
+    ) -> eyre::Result<StreamEnd> {
+        let deadline = async {
+            match until {
+                Some(until) => sleep_until(until).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline);
+
+        let mut head_interval = interval(self.config.head_poll_interval());
+        head_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = self.cancel.cancelled() => {
+                    return Ok(StreamEnd::Cancelled)
+                }
+                _ = &mut deadline => {
+                    return Ok(StreamEnd::Interrupted)
+                }
+                _ = head_interval.tick() => {
+                    match self.provider.get_block_number().await {
+                        Ok(head) => {
+                            // Blocks without events are complete too, once confirmed,
+                            // so that restart after quiet period doesn't backfill them
+                            if self.release(head).await? {
+                                self.checkpoint
+                                    .forwarded(self.confirmations.safe_block(head))?;
+                            }
                         }
-                        if let Ok(event) = log.log_decode::<IVaultNativeClaims::AcquisitionClaim>() {
-                            let event = event.data();
-                            sender
-                                .send(ChainMessage::AcquisitionClaim {
-                                    keeper: event.keeper,
-                                    trader: event.trader,
-                                    index_id: event.index_id,
-                                    vendor_id: event.vendor_id,
-                                    remain: event.remain,
-                                    spent: event.spent
-                                })
-                                .context("Failed to send chain event")?;
+                        Err(err) => {
+                            warn!("Failed to obtain block number: {:?}", err);
                         }
-                        if let Ok(event) = log.log_decode::<IVaultNativeClaims::DisposalClaim>() {
-                            let event = event.data();
-                            sender
-                                .send(ChainMessage::DisposalClaim {
-                                    keeper: event.keeper,
-                                    trader: event.trader,
-                                    index_id: event.index_id,
-                                    vendor_id: event.vendor_id,
-                                    itp_remain: event.itp_remain,
-                                    itp_burned: event.itp_burned 
-                                })
-                                .context("Failed to send chain event")?;
+                    }
+                }
+                log = stream.next() => {
+                    let Some(log) = log else {
+                        return Ok(StreamEnd::Interrupted)
+                    };
+
+                    // Logs already forwarded while backfilling are dropped by
+                    // `SeenLogs`, while logs included again after reorg are not
+                    let block_number = log.block_number;
+                    self.accept(log)?;
+
+                    // Without confirmations log is released as soon as it arrives
+                    if self.confirmations.depth() == 0
+                        && let Some(block_number) = block_number
+                    {
+                        self.release(block_number).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Put log in confirmation queue, or handle its removal by reorg
+    fn accept(&mut self, log: Log) -> eyre::Result<()> {
+        let tx_hash = log.transaction_hash;
+
+        match self.confirmations.push(log) {
+            Pushed::Pending => {}
+            Pushed::Retracted => {
+                warn!(?tx_hash, "♻️  Dropped unconfirmed log removed by reorg");
+            }
+            Pushed::Ignored => {
+                debug!(?tx_hash, "Dropped removed log, which was never forwarded");
+            }
+            Pushed::Orphaned { block_number } => {
+                warn!(?tx_hash, %block_number, "♻️  Forwarded log was removed by reorg");
+                let meta = EventMeta {
+                    block_number: Some(block_number),
+                    tx_hash,
+                    ..Default::default()
+                };
+                self.sender
+                    .send(ChainEvent {
+                        meta,
+                        message: ChainMessage::Reorg { block_number },
+                    })
+                    .context("Failed to send chain event")?;
+                self.checkpoint.sent();
+            }
+        }
 
+        Ok(())
+    }
+
+    /// Forward logs confirmed at given head
+    ///
+    /// Returns false if confirmed logs could not be verified against canonical
+    /// chain, in which case they are kept pending.
+    async fn release(&mut self, head: u64) -> eyre::Result<bool> {
+        let logs = self.confirmations.release(head);
+        if logs.is_empty() {
+            return Ok(true);
+        }
+
+        let mut orphaned_blocks = HashSet::new();
+
+        if 0 < self.confirmations.depth() {
+            let blocks: BTreeMap<u64, Option<B256>> = logs
+                .iter()
+                .filter_map(|log| Some((log.block_number?, log.block_hash)))
+                .collect();
+
+            for (block_number, block_hash) in blocks {
+                let canonical_hash = match self
+                    .provider
+                    .get_block_by_number(BlockNumberOrTag::Number(block_number))
+                    .await
+                {
+                    Ok(block) => block.map(|block| {
+                        self.remember_timestamp(block_number, block.header.timestamp);
+                        block.header.hash
+                    }),
+                    Err(err) => {
+                        warn!(%block_number, "Failed to verify block: {:?}", err);
+                        for log in logs {
+                            self.confirmations.push(log);
                         }
+                        return Ok(false);
                     }
+                };
+
+                if canonical_hash != block_hash {
+                    warn!(%block_number, "♻️  Dropped logs from block orphaned by reorg");
+                    orphaned_blocks.insert(block_number);
                 }
             }
         }
+
+        for log in logs {
+            let block_number = log.block_number.unwrap_or_default();
+            if orphaned_blocks.contains(&block_number) {
+                continue;
+            }
+
+            if self.forward_log(&log).await? {
+                self.confirmations.forwarded(&log);
+            }
+
+            // Other logs from the same block may follow, so block is complete
+            // only up to this log
+            match log.log_index {
+                Some(log_index) => self.checkpoint.forwarded_log(block_number, log_index)?,
+                None => self.checkpoint.forwarded(block_number.saturating_sub(1))?,
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Send event decoded from log to App, returning false if log is not one of the events
+    ///
+    /// Logs forwarded before, or processed before restart, are not sent again.
+    async fn forward_log(&mut self, log: &Log) -> eyre::Result<bool> {
+        let Some(message) = Self::decode_log(log) else {
+            return Ok(false);
+        };
+
+        let mut meta = EventMeta::from_log(log);
+
+        if let (Some(block_number), Some(log_index)) = (meta.block_number, meta.log_index)
+            && self
+                .resume_after
+                .is_some_and(|last| (block_number, log_index) <= last)
+        {
+            debug!(event = %meta, "Dropped log processed before restart");
+            return Ok(true);
+        }
+
+        if !self.seen_logs.insert(&meta) {
+            debug!(event = %meta, "Dropped duplicate log");
+            return Ok(true);
+        }
+
+        if meta.timestamp.is_none()
+            && let Some(block_number) = meta.block_number
+        {
+            meta.timestamp = self.block_timestamp(block_number).await;
+        }
+
+        self.sender
+            .send(ChainEvent { meta, message })
+            .context("Failed to send chain event")?;
+        self.checkpoint.sent();
+
+        Ok(true)
+    }
+
+    fn remember_timestamp(&mut self, block_number: u64, timestamp: u64) {
+        self.block_timestamps.insert(block_number, timestamp);
+        while BLOCK_TIMESTAMPS_CAPACITY < self.block_timestamps.len() {
+            self.block_timestamps.pop_first();
+        }
+    }
+
+    /// Timestamp of the block, for nodes that don't include it in logs
+    async fn block_timestamp(&mut self, block_number: u64) -> Option<u64> {
+        if let Some(timestamp) = self.block_timestamps.get(&block_number) {
+            return Some(*timestamp);
+        }
+
+        match self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .await
+        {
+            Ok(Some(block)) => {
+                self.remember_timestamp(block_number, block.header.timestamp);
+                Some(block.header.timestamp)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!(%block_number, "Failed to obtain block timestamp: {:?}", err);
+                None
+            }
+        }
+    }
+
+    fn decode_log(log: &Log) -> Option<ChainMessage> {
+        if let Ok(event) = log.log_decode::<IVaultNativeOrders::BuyOrder>() {
+            let event = event.data();
+            return Some(ChainMessage::BuyOrder {
+                keeper: event.keeper,
+                trader: event.trader,
+                index_id: event.index_id,
+                vendor_id: event.vendor_id,
+                collateral: event.collateral_amount,
+            });
+        }
+
+        if let Ok(event) = log.log_decode::<IVaultNativeOrders::SellOrder>() {
+            let event = event.data();
+            return Some(ChainMessage::SellOrder {
+                keeper: event.keeper,
+                trader: event.trader,
+                index_id: event.index_id,
+                vendor_id: event.vendor_id,
+                itp_amount: event.itp_amount,
+            });
+        }
+
+        if let Ok(event) = log.log_decode::<IVaultNativeOrders::Acquisition>() {
+            let event = event.data();
+            return Some(ChainMessage::Acquisition {
+                controller: event.controller,
+                index_id: event.index_id,
+                vendor_id: event.vendor_id,
+                remain: event.remain,
+                spent: event.spent,
+                minted: event.itp_minted,
+            });
+        }
+
+        if let Ok(event) = log.log_decode::<IVaultNativeOrders::Disposal>() {
+            let event = event.data();
+            return Some(ChainMessage::Disposal {
+                controller: event.controller,
+                index_id: event.index_id,
+                vendor_id: event.vendor_id,
+                remain: event.itp_remain,
+                burned: event.itp_burned,
+                gains: event.gains,
+            });
+        }
+
+        if let Ok(event) = log.log_decode::<IVaultNativeClaims::AcquisitionClaim>() {
+            let event = event.data();
+            return Some(ChainMessage::AcquisitionClaim {
+                keeper: event.keeper,
+                trader: event.trader,
+                index_id: event.index_id,
+                vendor_id: event.vendor_id,
+                remain: event.remain,
+                spent: event.spent,
+            });
+        }
+
+        if let Ok(event) = log.log_decode::<IVaultNativeClaims::DisposalClaim>() {
+            let event = event.data();
+            return Some(ChainMessage::DisposalClaim {
+                keeper: event.keeper,
+                trader: event.trader,
+                index_id: event.index_id,
+                vendor_id: event.vendor_id,
+                itp_remain: event.itp_remain,
+                itp_burned: event.itp_burned,
+            });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn meta(block_hash: u8, log_index: u64) -> EventMeta {
+        EventMeta {
+            block_hash: Some(B256::repeat_byte(block_hash)),
+            log_index: Some(log_index),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_seen_logs() {
+        let mut seen_logs = SeenLogs::new(2);
+
+        assert!(seen_logs.insert(&meta(1, 0)));
+        assert!(!seen_logs.insert(&meta(1, 0)));
+        assert!(seen_logs.insert(&meta(1, 1)));
+        assert!(seen_logs.insert(&meta(2, 0)));
+
+        // Same log included again in another block is not duplicate
+        let mut reincluded = meta(1, 1);
+        reincluded.block_hash = Some(B256::repeat_byte(3));
+        assert!(seen_logs.insert(&reincluded));
+
+        // Oldest entry evicted once capacity is exceeded
+        assert!(seen_logs.insert(&meta(1, 0)));
+        assert!(seen_logs.insert(&EventMeta::default()));
+        assert!(seen_logs.insert(&EventMeta::default()));
     }
 }
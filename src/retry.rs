@@ -0,0 +1,250 @@
+use std::{fmt::Display, time::Duration};
+
+use alloy::{
+    providers::PendingTransactionError,
+    transports::{RpcError, TransportError, TransportErrorKind},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConveyorError;
+
+/// What kind of failure caused an error, deciding whether it is worth retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// Connection dropped, rate limited, node temporarily unavailable
+    Transient,
+    /// Nonce too low / too high, or replacement underpriced
+    Nonce,
+    /// Call or transaction reverted
+    Revert,
+    /// Anything else
+    Unknown,
+}
+
+impl Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ErrorClass::Transient => "transient",
+            ErrorClass::Nonce => "nonce",
+            ErrorClass::Revert => "revert",
+            ErrorClass::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+const NONCE_MESSAGES: &[&str] = &[
+    "nonce too low",
+    "nonce too high",
+    "invalid nonce",
+    "replacement transaction underpriced",
+    "already known",
+];
+
+const REVERT_MESSAGES: &[&str] = &["revert", "out of gas", "invalid opcode"];
+
+const TRANSIENT_MESSAGES: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection",
+    "rate limit",
+    "too many requests",
+    "temporarily unavailable",
+    "backend connection task has stopped",
+];
+
+fn classify_message(message: &str) -> Option<ErrorClass> {
+    let message = message.to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|x| message.contains(x));
+
+    if matches(NONCE_MESSAGES) {
+        Some(ErrorClass::Nonce)
+    } else if matches(REVERT_MESSAGES) {
+        Some(ErrorClass::Revert)
+    } else if matches(TRANSIENT_MESSAGES) {
+        Some(ErrorClass::Transient)
+    } else {
+        None
+    }
+}
+
+fn classify_transport(err: &TransportError) -> ErrorClass {
+    match err {
+        RpcError::ErrorResp(payload) => {
+            if payload.as_revert_data().is_some() {
+                return ErrorClass::Revert;
+            }
+            classify_message(&payload.message).unwrap_or(if payload.is_retry_err() {
+                ErrorClass::Transient
+            } else {
+                ErrorClass::Unknown
+            })
+        }
+        RpcError::Transport(TransportErrorKind::Custom(inner)) => {
+            classify_message(&inner.to_string()).unwrap_or(ErrorClass::Transient)
+        }
+        RpcError::Transport(_) | RpcError::NullResp => ErrorClass::Transient,
+        _ => ErrorClass::Unknown,
+    }
+}
+
+fn classify_pending(err: &PendingTransactionError) -> ErrorClass {
+    match err {
+        PendingTransactionError::TransportError(err) => classify_transport(err),
+        _ => ErrorClass::Transient,
+    }
+}
+
+/// Classify error by looking through its chain of causes
+///
+/// Typed alloy errors are inspected first, and otherwise messages of all
+/// causes are matched against well-known node error messages.
+pub fn classify(err: &eyre::Report) -> ErrorClass {
+    for cause in err.chain() {
+        if cause.downcast_ref::<ConveyorError>().is_some() {
+            return ErrorClass::Revert;
+        }
+        if let Some(err) = cause.downcast_ref::<alloy::contract::Error>() {
+            return match err {
+                alloy::contract::Error::TransportError(err) => classify_transport(err),
+                alloy::contract::Error::PendingTransactionError(err) => classify_pending(err),
+                alloy::contract::Error::ZeroData(..) | alloy::contract::Error::AbiError(_) => {
+                    ErrorClass::Revert
+                }
+                _ => ErrorClass::Unknown,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<PendingTransactionError>() {
+            return classify_pending(err);
+        }
+        if let Some(err) = cause.downcast_ref::<TransportError>() {
+            return classify_transport(err);
+        }
+    }
+
+    err.chain()
+        .find_map(|cause| classify_message(&cause.to_string()))
+        .or_else(|| classify_message(&format!("{:?}", err)))
+        .unwrap_or(ErrorClass::Unknown)
+}
+
+const OUT_OF_GAS_MESSAGES: &[&str] = &[
+    "out of gas",
+    "gas required exceeds",
+    "exceeds block gas limit",
+];
+
+/// Whether transaction failed, or would fail, because it needs more gas than allowed
+pub fn is_out_of_gas(err: &eyre::Report) -> bool {
+    err.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        OUT_OF_GAS_MESSAGES.iter().any(|x| message.contains(x))
+    })
+}
+
+/// How many times to attempt, and how long to wait between attempts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before first retry, doubled on each next retry
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, backoff_ms: u64, max_backoff_ms: u64) -> Self {
+        Self {
+            max_attempts,
+            backoff_ms,
+            max_backoff_ms,
+        }
+    }
+
+    /// Delay before next attempt, or None if given attempt was the last one
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts <= attempt {
+            return None;
+        }
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        let backoff_ms = self
+            .backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms);
+        Some(Duration::from_millis(backoff_ms))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(1, 0, 0)
+    }
+}
+
+/// Retry policy for each class of errors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub transient: RetryPolicy,
+    pub nonce: RetryPolicy,
+    pub revert: RetryPolicy,
+    pub unknown: RetryPolicy,
+}
+
+impl RetryConfig {
+    pub fn policy(&self, class: ErrorClass) -> &RetryPolicy {
+        match class {
+            ErrorClass::Transient => &self.transient,
+            ErrorClass::Nonce => &self.nonce,
+            ErrorClass::Revert => &self.revert,
+            ErrorClass::Unknown => &self.unknown,
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            transient: RetryPolicy::new(5, 500, 10_000),
+            nonce: RetryPolicy::new(3, 1_000, 5_000),
+            revert: RetryPolicy::new(2, 2_000, 2_000),
+            unknown: RetryPolicy::new(1, 0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let err = eyre::eyre!("nonce too low: next nonce 5, tx nonce 4")
+            .wrap_err("Failed to process pending Buy order");
+        assert_eq!(classify(&err), ErrorClass::Nonce);
+
+        let err = eyre::eyre!("Failed to process pending Buy order: execution reverted");
+        assert_eq!(classify(&err), ErrorClass::Revert);
+
+        let err = eyre::Report::new(ConveyorError::Revert("Not enough liquidity".to_owned()))
+            .wrap_err("Transaction placeBuyOrder reverted");
+        assert_eq!(classify(&err), ErrorClass::Revert);
+
+        let err = eyre::Report::new(TransportErrorKind::backend_gone());
+        assert_eq!(classify(&err), ErrorClass::Transient);
+
+        let err = eyre::eyre!("Index 1001 has no vault");
+        assert_eq!(classify(&err), ErrorClass::Unknown);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(4, 100, 250);
+        assert_eq!(policy.backoff(1), Some(Duration::from_millis(100)));
+        assert_eq!(policy.backoff(2), Some(Duration::from_millis(200)));
+        assert_eq!(policy.backoff(3), Some(Duration::from_millis(250)));
+        assert_eq!(policy.backoff(4), None);
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt::Display;
+
+use alloy::{
+    primitives::{Address, B256},
+    providers::Provider,
+};
+use eyre::Context;
+use itertools::Itertools;
+
+use crate::interfaces::{castle::ICastle, constable::IConstable};
+
+/// Role granted by Castle, which Conveyor signers need
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Role {
+    Issuer,
+    Keeper,
+    Vendor,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Issuer, Role::Keeper, Role::Vendor];
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Issuer => write!(f, "ISSUER_ROLE"),
+            Role::Keeper => write!(f, "KEEPER_ROLE"),
+            Role::Vendor => write!(f, "VENDOR_ROLE"),
+        }
+    }
+}
+
+/// Role ids, as resolved by Constable
+#[derive(Debug, Clone, Copy)]
+pub struct RoleIds {
+    pub issuer: B256,
+    pub keeper: B256,
+    pub vendor: B256,
+}
+
+impl RoleIds {
+    pub async fn fetch<P: Provider>(provider: &P, castle_address: Address) -> eyre::Result<Self> {
+        let constable = IConstable::new(castle_address, provider);
+
+        let issuer = constable
+            .getIssuerRole()
+            .call()
+            .await
+            .context("Failed to obtain issuer role")?;
+
+        let keeper = constable
+            .getKeeperRole()
+            .call()
+            .await
+            .context("Failed to obtain keeper role")?;
+
+        let vendor = constable
+            .getVendorRole()
+            .call()
+            .await
+            .context("Failed to obtain vendor role")?;
+
+        Ok(Self {
+            issuer,
+            keeper,
+            vendor,
+        })
+    }
+
+    pub fn get(&self, role: Role) -> B256 {
+        match role {
+            Role::Issuer => self.issuer,
+            Role::Keeper => self.keeper,
+            Role::Vendor => self.vendor,
+        }
+    }
+}
+
+/// Roles of a signer, compared to those it needs
+///
+/// Roles in `optional` are neither required nor reported as excess.
+#[derive(Debug, Clone)]
+pub struct RoleCheck {
+    pub address: Address,
+    pub required: Vec<Role>,
+    pub optional: Vec<Role>,
+    pub granted: Vec<Role>,
+}
+
+impl RoleCheck {
+    pub async fn fetch<P: Provider>(
+        provider: &P,
+        castle_address: Address,
+        role_ids: &RoleIds,
+        address: Address,
+        required: Vec<Role>,
+        optional: Vec<Role>,
+    ) -> eyre::Result<Self> {
+        let castle = ICastle::new(castle_address, provider);
+
+        let mut granted = Vec::new();
+        for role in Role::ALL {
+            let has_role = castle
+                .hasRole(role_ids.get(role), address)
+                .call()
+                .await
+                .with_context(|| format!("Failed to check {} of {}", role, address))?;
+            if has_role {
+                granted.push(role);
+            }
+        }
+
+        Ok(Self {
+            address,
+            required,
+            optional,
+            granted,
+        })
+    }
+
+    /// Roles needed, but not granted
+    pub fn missing(&self) -> Vec<Role> {
+        self.required
+            .iter()
+            .filter(|x| !self.granted.contains(x))
+            .copied()
+            .collect()
+    }
+
+    /// Roles granted, but not needed
+    pub fn excess(&self) -> Vec<Role> {
+        self.granted
+            .iter()
+            .filter(|x| !self.required.contains(x) && !self.optional.contains(x))
+            .copied()
+            .collect()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.missing().is_empty() && self.excess().is_empty()
+    }
+}
+
+impl Display for RoleCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} has [{}]",
+            self.address,
+            self.granted.iter().join(", ")
+        )?;
+        let missing = self.missing();
+        if !missing.is_empty() {
+            write!(f, ", missing [{}]", missing.iter().join(", "))?;
+        }
+        let excess = self.excess();
+        if !excess.is_empty() {
+            write!(f, ", not needed [{}]", excess.iter().join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_role_check() {
+        let check = RoleCheck {
+            address: Address::ZERO,
+            required: vec![Role::Keeper],
+            optional: vec![Role::Issuer],
+            granted: vec![Role::Issuer, Role::Vendor],
+        };
+        assert_eq!(check.missing(), vec![Role::Keeper]);
+        assert_eq!(check.excess(), vec![Role::Vendor]);
+        assert!(!check.is_ok());
+        assert_eq!(
+            check.to_string(),
+            format!(
+                "{} has [ISSUER_ROLE, VENDOR_ROLE], missing [KEEPER_ROLE], not needed [VENDOR_ROLE]",
+                Address::ZERO
+            )
+        );
+    }
+}
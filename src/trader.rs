@@ -0,0 +1,305 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::{Provider, WalletProvider},
+};
+use eyre::{Context, bail, eyre};
+use tracing::{info, warn};
+
+use crate::{
+    common::amount::Amount,
+    error::ConveyorError,
+    interfaces::{
+        steward::ISteward, treasury::ITreasury, vault::IVault, vault_native::IVaultNative,
+        vault_native_claims::IVaultNativeClaims, vault_native_orders::IVaultNativeOrders,
+    },
+    tx_manager::{TxManager, TxRequest},
+};
+
+pub struct Trader<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    provider: P,
+    tx_manager: TxManager<P>,
+    castle_address: Address,
+    collateral_address: Address,
+    vault_address: Address,
+    index_id: u128,
+}
+
+impl<P> Trader<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    pub fn new(
+        provider: P,
+        tx_manager: TxManager<P>,
+        castle_address: Address,
+        collateral_address: Address,
+        index_id: u128,
+    ) -> Self {
+        Self {
+            provider,
+            tx_manager,
+            castle_address,
+            collateral_address,
+            index_id,
+            vault_address: Address::ZERO,
+        }
+    }
+
+    pub fn get_index_id(&self) -> u128 {
+        self.index_id
+    }
+
+    pub fn get_vault_address(&self) -> Address {
+        self.vault_address
+    }
+
+    pub async fn attach(&mut self) -> eyre::Result<()> {
+        let steward = ISteward::new(self.castle_address, &self.provider);
+
+        let vault_address = steward
+            .getVault(self.index_id)
+            .call()
+            .await
+            .context("Failed to obtain vault")?;
+
+        if vault_address.is_zero() {
+            bail!("Index {} has no vault", self.index_id)
+        }
+
+        self.vault_address = vault_address;
+
+        Ok(())
+    }
+
+    /// Approve Vault to spend collateral, returning whether approval was only simulated
+    async fn approve_collateral(&self, collateral_amount: u128) -> eyre::Result<bool> {
+        let collateral = ITreasury::new(self.collateral_address, &self.provider);
+        let trader = self.provider.default_signer_address();
+
+        let allowance = collateral
+            .allowance(trader, self.vault_address)
+            .call()
+            .await
+            .context("Failed to obtain allowance")?;
+
+        if U256::from(collateral_amount) <= allowance {
+            return Ok(false);
+        }
+
+        info!("Approving collateral...");
+        let approve = collateral.approve(self.vault_address, U256::from(collateral_amount));
+
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(approve))
+            .await
+            .context("Failed to approve collateral")?;
+
+        Ok(self.tx_manager.is_dry_run())
+    }
+
+    pub async fn place_buy_order(
+        &self,
+        keeper: Address,
+        collateral_amount: u128,
+        instant_fill: bool,
+    ) -> eyre::Result<()> {
+        info!("🛒 Handle: PlaceBuyOrder");
+        let vault = IVaultNativeOrders::new(self.vault_address, &self.provider);
+        let trader = self.provider.default_signer_address();
+
+        if self.approve_collateral(collateral_amount).await? {
+            // Order would revert on allowance, which was never granted
+            warn!(
+                "🧪 Dry-run: Buy order not simulated, since collateral approval was only simulated"
+            );
+            return Ok(());
+        }
+
+        let place_order_call = vault.placeBuyOrder(collateral_amount, instant_fill, keeper, trader);
+
+        let result = place_order_call
+            .call()
+            .await
+            .map_err(|err| ConveyorError::decode_report(err.into()))
+            .context("Failed to call place Buy order")?;
+
+        info!(
+            %trader,
+            collateral = %Amount::from_u128_raw(collateral_amount),
+            result_0 = %Amount::from_u128_raw(result._0),
+            result_1 = %Amount::from_u128_raw(result._1),
+            result_2 = %Amount::from_u128_raw(result._2),
+            "Placing Buy order..."
+        );
+
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(place_order_call))
+            .await
+            .context("Failed to place Buy order")?;
+
+        Ok(())
+    }
+
+    pub async fn place_sell_order(
+        &self,
+        keeper: Address,
+        itp_amount: u128,
+        instant_fill: bool,
+    ) -> eyre::Result<()> {
+        info!("🛒 Handle: PlaceSellOrder");
+        let vault = IVaultNativeOrders::new(self.vault_address, &self.provider);
+        let trader = self.provider.default_signer_address();
+
+        let place_order_call = vault.placeSellOrder(itp_amount, instant_fill, keeper, trader);
+
+        let result = place_order_call
+            .call()
+            .await
+            .map_err(|err| ConveyorError::decode_report(err.into()))
+            .context("Failed to call place Sell order")?;
+
+        info!(
+            %trader,
+            itp_amount = %Amount::from_u128_raw(itp_amount),
+            result_0 = %Amount::from_u128_raw(result._0),
+            result_1 = %Amount::from_u128_raw(result._1),
+            result_2 = %Amount::from_u128_raw(result._2),
+            "Placing Sell order..."
+        );
+
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(place_order_call))
+            .await
+            .context("Failed to place Sell order")?;
+
+        Ok(())
+    }
+
+    pub async fn claim_acquisition(
+        &self,
+        keeper: Address,
+        collateral_amount: u128,
+    ) -> eyre::Result<()> {
+        info!("🎟️  Handle: ClaimAcquisition");
+        let vault = IVaultNativeClaims::new(self.vault_address, &self.provider);
+        let trader = self.provider.default_signer_address();
+
+        let claim_call = vault.claimAcquisition(collateral_amount, keeper, trader);
+
+        let itp_amount = claim_call
+            .call()
+            .await
+            .map_err(|err| ConveyorError::decode_report(err.into()))
+            .context("Failed to call claim acquisition")?;
+
+        info!(
+            %trader,
+            collateral = %Amount::from_u128_raw(collateral_amount),
+            itp_amount = %Amount::from_u128_raw(itp_amount),
+            "Claiming acquisition..."
+        );
+
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(claim_call))
+            .await
+            .context("Failed to claim acquisition")?;
+
+        Ok(())
+    }
+
+    pub async fn claim_disposal(&self, keeper: Address, itp_amount: u128) -> eyre::Result<()> {
+        info!("🎟️  Handle: ClaimDisposal");
+        let vault = IVaultNativeClaims::new(self.vault_address, &self.provider);
+        let trader = self.provider.default_signer_address();
+
+        let claim_call = vault.claimDisposal(itp_amount, keeper, trader);
+
+        let gains = claim_call
+            .call()
+            .await
+            .map_err(|err| ConveyorError::decode_report(err.into()))
+            .context("Failed to call claim disposal")?;
+
+        info!(
+            %trader,
+            itp_amount = %Amount::from_u128_raw(itp_amount),
+            gains = %Amount::from_u128_raw(gains),
+            "Claiming disposal..."
+        );
+
+        self.tx_manager
+            .send_and_confirm(TxRequest::from_call(claim_call))
+            .await
+            .context("Failed to claim disposal")?;
+
+        Ok(())
+    }
+
+    pub async fn log_claims(&self, keeper: Address, trader: Address) -> eyre::Result<()> {
+        let vault = IVaultNativeClaims::new(self.vault_address, &self.provider);
+
+        let pending = vault
+            .getPendingOrder(keeper, trader)
+            .call()
+            .await
+            .context("Failed to obtain pending order")?;
+
+        let acquisition = vault
+            .getClaimableAcquisition(keeper)
+            .call()
+            .await
+            .context("Failed to obtain claimable acquisition")?;
+
+        let disposal = vault
+            .getClaimableDisposal(keeper)
+            .call()
+            .await
+            .context("Failed to obtain claimable disposal")?;
+
+        info!(
+            %keeper,
+            %trader,
+            pending_0 = %Amount::from_u128_raw(pending._0),
+            pending_1 = %Amount::from_u128_raw(pending._1),
+            acquisition_0 = %Amount::from_u128_raw(acquisition._0),
+            acquisition_1 = %Amount::from_u128_raw(acquisition._1),
+            disposal_0 = %Amount::from_u128_raw(disposal._0),
+            disposal_1 = %Amount::from_u128_raw(disposal._1),
+            "🎟️  Claims"
+        );
+
+        Ok(())
+    }
+
+    pub async fn log_balance(&self, trader: Address) -> eyre::Result<()> {
+        let vault = IVault::new(self.vault_address, &self.provider);
+        let vault_native = IVaultNative::new(self.vault_address, &self.provider);
+
+        let balance = vault
+            .balanceOf(trader)
+            .call()
+            .await
+            .context("Failed to obtain balance")?;
+
+        let assets_value = vault_native
+            .assetsValue(trader)
+            .call()
+            .await
+            .context("Failed to obtain assets value")?;
+
+        let balance = Amount::try_from_u256(balance)
+            .ok_or_else(|| eyre!("Balance of {} is out of range: {}", trader, balance))?;
+
+        info!(
+            %trader,
+            %balance,
+            assets_value = %Amount::from_u128_raw(assets_value),
+            "👛 Balance"
+        );
+
+        Ok(())
+    }
+}
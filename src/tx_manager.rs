@@ -0,0 +1,608 @@
+use std::sync::Arc;
+
+use alloy::{
+    consensus::Transaction,
+    contract::SolCallBuilder,
+    dyn_abi::{DynSolType, DynSolValue},
+    eips::eip1559::Eip1559Estimation,
+    network::TransactionBuilder,
+    primitives::TxHash,
+    providers::{
+        PendingTransactionConfig, PendingTransactionError, Provider, WalletProvider, WatchTxError,
+    },
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    sol_types::{SolCall, SolType},
+};
+use eyre::{Context, bail};
+use futures_util::{StreamExt, stream::FuturesUnordered};
+use itertools::Itertools;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::TxConfig,
+    error::ConveyorError,
+    metrics::Metrics,
+    retry::{ErrorClass, classify},
+};
+
+/// Nodes reject replacement transactions raising fees by less than this
+const MIN_FEE_BUMP_PERCENT: u64 = 10;
+
+/// State-changing contract call to be sent as transaction
+pub struct TxRequest {
+    /// Name of the contract function
+    pub label: String,
+    /// Solidity types returned by the function, used to decode simulated result
+    pub returns: &'static str,
+    pub tx: TransactionRequest,
+}
+
+impl TxRequest {
+    pub fn from_call<P, C>(call: SolCallBuilder<P, C>) -> Self
+    where
+        C: SolCall,
+    {
+        Self {
+            label: C::SIGNATURE
+                .split('(')
+                .next()
+                .unwrap_or(C::SIGNATURE)
+                .to_owned(),
+            returns: <C::ReturnTuple<'static> as SolType>::SOL_NAME,
+            tx: call.into_transaction_request(),
+        }
+    }
+}
+
+/// Result of a transaction, or of its simulation in dry-run mode
+pub enum TxOutcome {
+    Mined(Box<TransactionReceipt>),
+    /// Values returned by the simulated call
+    Simulated(String),
+}
+
+/// Transaction sent, but not yet confirmed
+pub struct PendingTx {
+    pub label: String,
+    pub nonce: u64,
+    /// Request as last sent, i.e. with nonce and fees set
+    tx: TransactionRequest,
+    /// Hashes of the original and all replacement transactions
+    tx_hashes: Vec<TxHash>,
+    /// Values returned by the simulated call, when in dry-run mode
+    simulated: Option<String>,
+}
+
+/// Limit fees to configured caps
+pub fn cap_fees(config: &TxConfig, fees: Eip1559Estimation) -> Eip1559Estimation {
+    let max_fee_per_gas = config
+        .max_fee_per_gas
+        .map_or(fees.max_fee_per_gas, |cap| fees.max_fee_per_gas.min(cap));
+    let max_priority_fee_per_gas = config
+        .max_priority_fee_per_gas
+        .map_or(fees.max_priority_fee_per_gas, |cap| {
+            fees.max_priority_fee_per_gas.min(cap)
+        })
+        .min(max_fee_per_gas);
+    Eip1559Estimation {
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    }
+}
+
+/// Fees of replacement transaction, or None if caps don't allow sufficient bump
+///
+/// Nodes accept replacement only if both fees are raised by at least 10%, so
+/// previous fees are bumped by `fee_bump_percent`, unless current estimate is
+/// even higher.
+pub fn bump_fees(
+    config: &TxConfig,
+    previous: Eip1559Estimation,
+    estimate: Eip1559Estimation,
+) -> Option<Eip1559Estimation> {
+    let percent = config.fee_bump_percent.max(MIN_FEE_BUMP_PERCENT) as u128;
+    let bump = |fee: u128| fee + (fee * percent).div_ceil(100);
+
+    let max_fee_per_gas = bump(previous.max_fee_per_gas).max(estimate.max_fee_per_gas);
+    let max_priority_fee_per_gas =
+        bump(previous.max_priority_fee_per_gas).max(estimate.max_priority_fee_per_gas);
+
+    let fees = cap_fees(
+        config,
+        Eip1559Estimation {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        },
+    );
+
+    let min_fee = |fee: u128| fee + (fee * MIN_FEE_BUMP_PERCENT as u128).div_ceil(100);
+    if fees.max_fee_per_gas < min_fee(previous.max_fee_per_gas)
+        || fees.max_priority_fee_per_gas < min_fee(previous.max_priority_fee_per_gas)
+    {
+        return None;
+    }
+
+    Some(fees)
+}
+
+/// Sends all transactions of the signer, assigning nonces locally
+///
+/// Nonces are assigned in the order transactions are sent, so that many of
+/// them can be in-flight at once, while their receipts are collected
+/// concurrently. Next nonce is re-synced from the chain whenever send or
+/// confirmation fails, so that a gap left by a failed transaction is filled.
+#[derive(Clone)]
+pub struct TxManager<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    provider: P,
+    config: TxConfig,
+    next_nonce: Arc<Mutex<Option<u64>>>,
+    metrics: Metrics,
+}
+
+impl<P> TxManager<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    pub fn new(provider: P, config: TxConfig) -> Self {
+        Self {
+            provider,
+            config,
+            next_nonce: Arc::new(Mutex::new(None)),
+            metrics: Metrics::new(),
+        }
+    }
+
+    /// Count transactions and gas used in given metrics
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn get_provider(&self) -> &P {
+        &self.provider
+    }
+
+    pub fn get_config(&self) -> &TxConfig {
+        &self.config
+    }
+
+    /// Forget next nonce, so that it is fetched from the chain on next send
+    pub async fn resync(&self) {
+        *self.next_nonce.lock().await = None;
+    }
+
+    /// Current fee estimate, limited by configured caps
+    async fn estimate_fees(&self) -> eyre::Result<Eip1559Estimation> {
+        let fees = self
+            .provider
+            .estimate_eip1559_fees()
+            .await
+            .context("Failed to estimate fees")?;
+        Ok(cap_fees(&self.config, fees))
+    }
+
+    async fn fetch_nonce(&self) -> eyre::Result<u64> {
+        self.provider
+            .get_transaction_count(self.provider.default_signer_address())
+            .pending()
+            .await
+            .context("Failed to obtain nonce")
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.config.dry_run
+    }
+
+    /// Simulate transaction with `eth_call`, and log the values it returned
+    async fn simulate(&self, request: TxRequest) -> eyre::Result<PendingTx> {
+        let TxRequest { label, returns, tx } = request;
+        let tx = tx.with_from(self.provider.default_signer_address());
+
+        let data = self
+            .provider
+            .call(tx.clone())
+            .await
+            .map_err(|err| ConveyorError::decode_report(err.into()))
+            .with_context(|| format!("Failed to simulate {}", label))?;
+
+        let values = DynSolType::parse(returns)
+            .and_then(|returns| returns.abi_decode_sequence(&data))
+            .map(|value| format_value(&value))
+            .unwrap_or_else(|_| data.to_string());
+
+        info!(%label, %values, "🧪 Dry-run");
+
+        Ok(PendingTx {
+            label,
+            nonce: 0,
+            tx,
+            tx_hashes: Vec::new(),
+            simulated: Some(values),
+        })
+    }
+
+    /// Send transaction with next nonce, without waiting for it to be mined
+    ///
+    /// In dry-run mode transaction is only simulated.
+    pub async fn send(&self, request: TxRequest) -> eyre::Result<PendingTx> {
+        let label = request.label.clone();
+        let result = if self.config.dry_run {
+            self.simulate(request).await
+        } else {
+            self.send_signed(request).await
+        };
+        if result.is_err() {
+            self.metrics.inc_transaction(&label, "failed");
+        }
+        result
+    }
+
+    async fn send_signed(&self, request: TxRequest) -> eyre::Result<PendingTx> {
+        let TxRequest { label, tx, .. } = request;
+
+        // Lock is held while sending, so that transactions reach node in nonce order
+        let mut next_nonce = self.next_nonce.lock().await;
+        let mut resynced = false;
+
+        let fees = self.estimate_fees().await?;
+        let tx = tx
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+
+        loop {
+            let nonce = match *next_nonce {
+                Some(nonce) => nonce,
+                None => self.fetch_nonce().await?,
+            };
+
+            let tx = tx.clone().with_nonce(nonce);
+            match self.provider.send_transaction(tx.clone()).await {
+                Ok(pending) => {
+                    *next_nonce = Some(nonce + 1);
+                    let tx_hash = *pending.tx_hash();
+                    debug!(
+                        %label,
+                        %nonce,
+                        %tx_hash,
+                        max_fee_per_gas = %fees.max_fee_per_gas,
+                        max_priority_fee_per_gas = %fees.max_priority_fee_per_gas,
+                        "Transaction sent"
+                    );
+                    return Ok(PendingTx {
+                        label,
+                        nonce,
+                        tx,
+                        tx_hashes: vec![tx_hash],
+                        simulated: None,
+                    });
+                }
+                Err(err) => {
+                    *next_nonce = None;
+                    let err = eyre::Report::new(err);
+                    if !resynced && classify(&err) == ErrorClass::Nonce {
+                        warn!(%label, %nonce, "Nonce rejected, re-syncing: {:?}", err);
+                        resynced = true;
+                        continue;
+                    }
+                    return Err(ConveyorError::decode_report(err))
+                        .with_context(|| format!("Failed to send {}", label));
+                }
+            }
+        }
+    }
+
+    /// Receipt of any of the transactions sent with the nonce, if one was mined
+    async fn find_receipt(&self, tx_hashes: &[TxHash]) -> Option<TransactionReceipt> {
+        for tx_hash in tx_hashes.iter().rev() {
+            if let Ok(Some(receipt)) = self.provider.get_transaction_receipt(*tx_hash).await {
+                return Some(receipt);
+            }
+        }
+        None
+    }
+
+    /// Send transaction again with same nonce and higher fees
+    async fn replace(&self, pending_tx: &mut PendingTx) -> eyre::Result<bool> {
+        let previous = Eip1559Estimation {
+            max_fee_per_gas: pending_tx.tx.max_fee_per_gas.unwrap_or_default(),
+            max_priority_fee_per_gas: pending_tx.tx.max_priority_fee_per_gas.unwrap_or_default(),
+        };
+
+        let Some(fees) = bump_fees(&self.config, previous, self.estimate_fees().await?) else {
+            warn!(
+                label = %pending_tx.label,
+                nonce = %pending_tx.nonce,
+                "Fee caps reached, cannot replace transaction"
+            );
+            return Ok(false);
+        };
+
+        let tx = pending_tx
+            .tx
+            .clone()
+            .with_max_fee_per_gas(fees.max_fee_per_gas)
+            .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+
+        let pending = self
+            .provider
+            .send_transaction(tx.clone())
+            .await
+            .with_context(|| format!("Failed to replace {}", pending_tx.label))?;
+
+        warn!(
+            label = %pending_tx.label,
+            nonce = %pending_tx.nonce,
+            tx_hash = %pending.tx_hash(),
+            max_fee_per_gas = %fees.max_fee_per_gas,
+            max_priority_fee_per_gas = %fees.max_priority_fee_per_gas,
+            "⏫ Transaction replaced with higher fees"
+        );
+
+        pending_tx.tx = tx;
+        pending_tx.tx_hashes.push(*pending.tx_hash());
+
+        Ok(true)
+    }
+
+    /// Replay reverted transaction with `eth_call` on top of its parent block to
+    /// find out why it reverted (receipts carry no revert data)
+    async fn revert_reason(
+        &self,
+        tx: TransactionRequest,
+        receipt: &TransactionReceipt,
+    ) -> Option<ConveyorError> {
+        let block_number = receipt.block_number?.checked_sub(1)?;
+        let tx = tx.with_from(self.provider.default_signer_address());
+        let err = self
+            .provider
+            .call(tx)
+            .block(block_number.into())
+            .await
+            .err()?;
+        ConveyorError::from_report(&eyre::Report::new(err))
+    }
+
+    /// Wait for transaction to be mined, and check that it succeeded
+    ///
+    /// Transaction not mined within `receipt_timeout_secs` is replaced with
+    /// one paying higher fees, up to `max_replacements` times.
+    pub async fn confirm(&self, mut pending_tx: PendingTx) -> eyre::Result<TxOutcome> {
+        if let Some(values) = pending_tx.simulated {
+            self.metrics.inc_transaction(&pending_tx.label, "simulated");
+            return Ok(TxOutcome::Simulated(values));
+        }
+
+        let timeout = self.config.receipt_timeout();
+        let mut replacements = 0;
+
+        let receipt = loop {
+            let tx_hash = pending_tx.tx_hashes.last().copied().unwrap_or_default();
+            let watch = self
+                .provider
+                .watch_pending_transaction(
+                    PendingTransactionConfig::new(tx_hash).with_timeout(Some(timeout)),
+                )
+                .await;
+
+            let result = match watch {
+                Ok(watch) => watch.await.map_err(eyre::Report::new),
+                Err(err) => Err(eyre::Report::new(err)),
+            };
+
+            match result {
+                Ok(_) => {
+                    if let Some(receipt) = self.find_receipt(&pending_tx.tx_hashes).await {
+                        break receipt;
+                    }
+                }
+                Err(err) if !is_timeout(&err) => {
+                    // Transaction may have been dropped, leaving a gap
+                    self.resync().await;
+                    self.metrics.inc_transaction(&pending_tx.label, "failed");
+                    return Err(err)
+                        .with_context(|| format!("Failed to confirm {}", pending_tx.label));
+                }
+                Err(_) => {}
+            }
+
+            // Transaction replaced before may have been mined in the meantime
+            if let Some(receipt) = self.find_receipt(&pending_tx.tx_hashes).await {
+                break receipt;
+            }
+
+            let replaced = if replacements < self.config.max_replacements {
+                match self.replace(&mut pending_tx).await {
+                    Ok(replaced) => replaced,
+                    Err(err) => {
+                        warn!(label = %pending_tx.label, "Failed to replace transaction: {:?}", err);
+                        false
+                    }
+                }
+            } else {
+                false
+            };
+
+            if !replaced {
+                self.resync().await;
+                self.metrics.inc_transaction(&pending_tx.label, "failed");
+                bail!(
+                    "Transaction {} with nonce {} not mined within {:?} (after {} replacements)",
+                    pending_tx.label,
+                    pending_tx.nonce,
+                    timeout,
+                    replacements
+                )
+            }
+
+            replacements += 1;
+        };
+
+        let PendingTx {
+            label, nonce, tx, ..
+        } = pending_tx;
+
+        info!(
+            %label,
+            %nonce,
+            tx_hash = %receipt.transaction_hash,
+            gas_used = %receipt.gas_used,
+            effective_gas_price = %receipt.effective_gas_price,
+            "⛽ Transaction mined"
+        );
+
+        self.metrics.inc_gas_used(&label, receipt.gas_used);
+
+        if !receipt.status() {
+            self.metrics.inc_transaction(&label, "reverted");
+            let gas_limit = self
+                .provider
+                .get_transaction_by_hash(receipt.transaction_hash)
+                .await
+                .ok()
+                .flatten()
+                .map(|tx| tx.inner.gas_limit());
+            if gas_limit.is_some_and(|gas_limit| gas_limit <= receipt.gas_used) {
+                bail!("Transaction {} ran out of gas: {:?}", label, receipt)
+            }
+            if let Some(reason) = self.revert_reason(tx, &receipt).await {
+                return Err(eyre::Report::new(reason))
+                    .with_context(|| format!("Transaction {} reverted", label));
+            }
+            bail!("Transaction {} reverted: {:?}", label, receipt)
+        }
+
+        self.metrics.inc_transaction(&label, "mined");
+        debug!(%label, %nonce, "Receipt: {:?}", receipt);
+
+        Ok(TxOutcome::Mined(Box::new(receipt)))
+    }
+
+    pub async fn send_and_confirm(&self, request: TxRequest) -> eyre::Result<TxOutcome> {
+        let pending_tx = self.send(request).await?;
+        self.confirm(pending_tx).await
+    }
+
+    /// Send independent transactions in a pipeline, and collect their receipts
+    ///
+    /// Up to `max_in_flight` transactions are awaiting receipts at once. If
+    /// sending fails no more transactions are sent (their results are None),
+    /// but receipts of those already sent are still collected. Results are
+    /// returned in the order of transactions.
+    pub async fn send_each(&self, txs: Vec<TxRequest>) -> Vec<Option<eyre::Result<TxOutcome>>> {
+        let max_in_flight = self.config.max_in_flight.max(1);
+
+        let mut results: Vec<Option<eyre::Result<TxOutcome>>> = txs.iter().map(|_| None).collect();
+        let mut in_flight = FuturesUnordered::new();
+
+        for (index, request) in txs.into_iter().enumerate() {
+            while max_in_flight <= in_flight.len() {
+                if let Some((index, result)) = in_flight.next().await {
+                    results[index] = Some(result);
+                }
+            }
+
+            match self.send(request).await {
+                Ok(pending_tx) => {
+                    in_flight.push(async move { (index, self.confirm(pending_tx).await) });
+                }
+                Err(err) => {
+                    results[index] = Some(Err(err));
+                    break;
+                }
+            }
+        }
+
+        while let Some((index, result)) = in_flight.next().await {
+            results[index] = Some(result);
+        }
+
+        results
+    }
+}
+
+/// Format decoded values, e.g. `(100, 0x1234..)`
+fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::Bool(x) => x.to_string(),
+        DynSolValue::Int(x, _) => x.to_string(),
+        DynSolValue::Uint(x, _) => x.to_string(),
+        DynSolValue::Address(x) => x.to_string(),
+        DynSolValue::String(x) => format!("{:?}", x),
+        DynSolValue::Bytes(x) => alloy::hex::encode_prefixed(x),
+        DynSolValue::FixedBytes(x, size) => alloy::hex::encode_prefixed(&x[..*size]),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+            format!("[{}]", values.iter().map(format_value).join(", "))
+        }
+        DynSolValue::Tuple(values) => {
+            format!("({})", values.iter().map(format_value).join(", "))
+        }
+        other => format!("{:?}", other),
+    }
+}
+
+fn is_timeout(err: &eyre::Report) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<PendingTransactionError>(),
+            Some(PendingTransactionError::TxWatcher(WatchTxError::Timeout))
+        ) || matches!(
+            cause.downcast_ref::<WatchTxError>(),
+            Some(WatchTxError::Timeout)
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> Eip1559Estimation {
+        Eip1559Estimation {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
+    }
+
+    #[test]
+    fn test_fees() {
+        let config = TxConfig {
+            max_fee_per_gas: Some(200),
+            max_priority_fee_per_gas: Some(20),
+            ..Default::default()
+        };
+
+        assert_eq!(cap_fees(&config, fees(300, 30)), fees(200, 20));
+        assert_eq!(cap_fees(&config, fees(100, 10)), fees(100, 10));
+
+        assert_eq!(
+            bump_fees(&config, fees(100, 10), fees(90, 5)),
+            Some(fees(115, 12))
+        );
+        assert_eq!(
+            bump_fees(&config, fees(100, 10), fees(150, 5)),
+            Some(fees(150, 12))
+        );
+        assert_eq!(bump_fees(&config, fees(190, 10), fees(190, 10)), None);
+    }
+
+    #[test]
+    fn test_format_value() {
+        let returns = DynSolType::parse("(uint128,uint128,bool)").unwrap();
+        let value = returns
+            .abi_decode_sequence(
+                &DynSolValue::Tuple(vec![
+                    DynSolValue::Uint(alloy::primitives::U256::from(100), 128),
+                    DynSolValue::Uint(alloy::primitives::U256::ZERO, 128),
+                    DynSolValue::Bool(true),
+                ])
+                .abi_encode_sequence()
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(format_value(&value), "(100, 0, true)");
+    }
+}
@@ -1,19 +1,27 @@
+use std::{collections::BTreeMap, ops::Range, time::Duration};
+
 use alloy::{
+    eips::BlockNumberOrTag,
+    network::TransactionBuilder,
     primitives::Address,
     providers::{Provider, WalletProvider},
 };
-use eyre::{Context, bail};
+use eyre::{Context, OptionExt, bail};
 use itertools::Itertools;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::{
-    common::{
-        constants::{DEMAND_LONG_OFFSET, DEMAND_SHORT_OFFSET},
-        labels::Labels,
-        rand_value::ValueGen,
-        vector::Vector,
-    },
+    chunking::{CallKind, ChunkSizes, ChunkingConfig, GasModel, split_range},
+    common::{labels::Labels, rand_value::ValueGen, vector::Vector},
+    config::VendorConfig,
+    hedging::Hedger,
     interfaces::{banker::IBanker, steward::ISteward},
+    inventory::{InventorySnapshot, VendorDelta, VendorDemand, VendorSupply},
+    ledger::{Ledger, LedgerEntry},
+    market::source::MarketDataSource,
+    retry::is_out_of_gas,
+    tx_manager::{TxManager, TxRequest},
+    venue::execution::ExecutionVenue,
 };
 
 pub struct Vendor<P>
@@ -21,34 +29,53 @@ where
     P: Provider + WalletProvider + Clone + 'static,
 {
     provider: P,
+    tx_manager: TxManager<P>,
     castle_address: Address,
     custody_address: Address,
     collateral_address: Address,
     vendor_id: u128,
     market_assets: Labels,
-    chunk_size: usize,
+    market_data: Box<dyn MarketDataSource>,
+    chunk_sizes: ChunkSizes,
+    chunking: ChunkingConfig,
+    venue: Box<dyn ExecutionVenue>,
+    hedger: Hedger,
+    ledger: Ledger,
 }
 
 impl<P> Vendor<P>
 where
     P: Provider + WalletProvider + Clone + 'static,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         provider: P,
+        tx_manager: TxManager<P>,
         castle_address: Address,
         custody_address: Address,
         collateral_address: Address,
         vendor_id: u128,
         chunk_size: usize,
+        chunking: ChunkingConfig,
+        market_data: Box<dyn MarketDataSource>,
+        venue: Box<dyn ExecutionVenue>,
+        hedger: Hedger,
+        ledger: Ledger,
     ) -> Self {
         Self {
             provider,
+            tx_manager,
             castle_address,
             custody_address,
             collateral_address,
             vendor_id,
-            chunk_size,
+            chunk_sizes: ChunkSizes::new(chunk_size),
+            chunking,
+            market_data,
             market_assets: Labels::new(),
+            venue,
+            hedger,
+            ledger,
         }
     }
 
@@ -56,6 +83,11 @@ where
         self.vendor_id
     }
 
+    /// Address of the wallet sending transactions
+    pub fn get_address(&self) -> Address {
+        self.provider.default_signer_address()
+    }
+
     pub fn get_market_assets(&self) -> &Labels {
         &self.market_assets
     }
@@ -64,216 +96,650 @@ where
         self.custody_address
     }
 
+    pub fn get_ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// Record entry in the ledger (failure to journal it is only logged)
+    pub fn record_ledger(&mut self, entry: LedgerEntry) {
+        if let Err(err) = self.ledger.record(entry) {
+            warn!("Failed to record ledger entry: {:?}", err);
+        }
+    }
+
     pub fn get_collateral_address(&self) -> Address {
         self.collateral_address
     }
 
-    pub async fn setup(&mut self, market_size: usize) -> eyre::Result<()> {
+    pub async fn setup(&mut self, config: &VendorConfig) -> eyre::Result<()> {
         info!("Handle: Vendor Setup");
 
-        let assets = Labels {
-            data: (1..market_size + 1)
-                .into_iter()
-                .map(|i| i as u128)
-                .collect_vec(),
+        let assets = if config.assets.is_empty() {
+            Labels {
+                data: (1..config.market_size + 1).map(|i| i as u128).collect_vec(),
+            }
+        } else {
+            Labels::from_vec_u128(config.assets.clone())
         };
 
-        for chunk in assets.data.chunks(self.chunk_size) {
-            self._submit_assets(chunk).await?;
-        }
+        let margin = if config.margins.is_empty() {
+            let mut margin_gen = ValueGen::new(1_00, 10_00, 2);
+            Vector {
+                data: assets.data.iter().map(|_| margin_gen.next()).collect_vec(),
+            }
+        } else {
+            Vector {
+                data: config.margins.clone(),
+            }
+        };
+
+        let banker = IBanker::new(self.castle_address, self.provider.clone());
+        let vendor_id = self.vendor_id;
+
+        // Margin can only be submitted for assets already submitted
+        info!("Submitting assets...");
+        self.submit_chunked(CallKind::SubmitAssets, assets.data.len(), |range| {
+            let asset_names = Labels::from_vec_u128(assets.data[range].to_vec());
+            TxRequest::from_call(banker.submitAssets(vendor_id, asset_names.to_vec().into()))
+        })
+        .await
+        .context("Failed to submit assets")?;
+
+        info!("Submitting margin...");
+        self.submit_chunked(CallKind::SubmitMargin, assets.data.len(), |range| {
+            let asset_names = Labels::from_vec_u128(assets.data[range.clone()].to_vec());
+            let margin = Vector {
+                data: margin.data[range].to_vec(),
+            };
+            TxRequest::from_call(banker.submitMargin(
+                vendor_id,
+                asset_names.to_vec().into(),
+                margin.to_vec().into(),
+            ))
+        })
+        .await
+        .context("Failed to submit margin")?;
+
+        // Supply and delta are validated against market assets, so these must be set first
+        self.market_assets = Labels::from_vec_u128(assets.data.clone());
 
         self.update_market(&assets).await?;
 
         self.update_supply().await?;
 
+        Ok(())
+    }
+
+    /// Rebuild Vendor state from assets that were already submitted on-chain
+    pub async fn attach(&mut self) -> eyre::Result<()> {
+        info!("Handle: Vendor Attach");
+        let steward = ISteward::new(self.castle_address, &self.provider);
+
+        let assets_bytes = steward
+            .getVendorAssets(self.vendor_id)
+            .call()
+            .await
+            .context("Failed to obtain vendor assets")?;
+
+        let assets = Labels::from_vec(assets_bytes);
+
+        if assets.data.is_empty() {
+            bail!("Vendor {} has no assets", self.vendor_id)
+        }
+
+        info!(
+            vendor_id = %self.vendor_id,
+            market_size = %assets.data.len(),
+            "Attached to Vendor"
+        );
+
         self.market_assets = assets;
 
         Ok(())
     }
 
-    async fn _submit_assets(&mut self, assets: &[u128]) -> eyre::Result<()> {
-        let banker = IBanker::new(self.castle_address, &self.provider);
-        let mut margin_gen = ValueGen::new(1_00, 10_00, 2);
-        let margin = Vector {
-            data: assets.iter().map(|_| margin_gen.next()).collect_vec(),
-        };
+    /// Chunk size of given call, derived from gas estimates on first use
+    async fn chunk_size<F>(&mut self, kind: CallKind, len: usize, build: &F) -> usize
+    where
+        F: Fn(Range<usize>) -> TxRequest,
+    {
+        if let Some(size) = self.chunk_sizes.get(kind) {
+            return size;
+        }
 
-        let asset_names = Labels::from_vec_u128(assets.to_vec());
+        let max_chunk_size = self.chunk_sizes.max_chunk_size();
+        if !self.chunking.auto {
+            return self.chunk_sizes.set(kind, max_chunk_size);
+        }
 
-        info!("Submitting assets...");
-        let submit_assets = banker
-            .submitAssets(self.vendor_id, asset_names.to_vec().into())
-            .send()
-            .await
-            .context("Failed to submit assets")?;
+        // Too few items to fit gas model, so try again next time
+        if len < 2 {
+            return max_chunk_size;
+        }
 
-        info!("Submitting margin...");
-        let submit_margin = banker
-            .submitMargin(
-                self.vendor_id,
-                asset_names.to_vec().into(),
-                margin.to_vec().into(),
-            )
-            .send()
+        match self
+            .estimate_chunk_size(len.min(max_chunk_size), build)
             .await
-            .context("Failed to submit margin")?;
+        {
+            Ok((model, gas_budget, size)) => {
+                let size = self.chunk_sizes.set(kind, size);
+                info!(
+                    %kind,
+                    base_gas = %model.base,
+                    gas_per_asset = %model.per_item,
+                    %gas_budget,
+                    chunk_size = %size,
+                    "⛽ Chunk size"
+                );
+                size
+            }
+            Err(err) => {
+                warn!(
+                    %kind,
+                    chunk_size = %max_chunk_size,
+                    "Failed to estimate chunk size: {:?}",
+                    err
+                );
+                max_chunk_size
+            }
+        }
+    }
 
-        let submit_assets_receipt = submit_assets
-            .get_receipt()
+    /// Fit gas model from estimates of two chunks, and find largest chunk fitting gas budget
+    async fn estimate_chunk_size<F>(
+        &self,
+        mut large: usize,
+        build: &F,
+    ) -> eyre::Result<(GasModel, u64, usize)>
+    where
+        F: Fn(Range<usize>) -> TxRequest,
+    {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Latest)
             .await
-            .context("Failed to confirm submit assets")?;
-
-        if !submit_assets_receipt.status() {
-            bail!("Failed to submit assets: {:?}", submit_assets_receipt)
+            .context("Failed to obtain latest block")?
+            .ok_or_eyre("Latest block not found")?;
+
+        let gas_budget = self.chunking.gas_budget(block.header.gas_limit);
+        let from = self.provider.default_signer_address();
+
+        while 2 <= large {
+            let small = large / 2;
+
+            let large_gas = match self
+                .provider
+                .estimate_gas(build(0..large).tx.with_from(from))
+                .await
+            {
+                Ok(gas) => gas,
+                Err(err) => {
+                    let err = eyre::Report::new(err);
+                    if is_out_of_gas(&err) {
+                        large = small;
+                        continue;
+                    }
+                    return Err(err);
+                }
+            };
+
+            let small_gas = self
+                .provider
+                .estimate_gas(build(0..small).tx.with_from(from))
+                .await?;
+
+            let model = GasModel::fit((small, small_gas), (large, large_gas))
+                .ok_or_eyre("Failed to fit gas model")?;
+
+            return Ok((model, gas_budget, model.max_items(gas_budget)));
         }
 
-        debug!("Submit assets receipt: {:?}", submit_assets_receipt);
+        bail!("Even smallest chunk exceeds gas limit")
+    }
 
-        let submit_margin_receipt = submit_margin
-            .get_receipt()
-            .await
-            .context("Failed to confirm submit margin")?;
+    /// Submit call for all items in chunks, shrinking chunks that ran out of gas
+    async fn submit_chunked<F>(&mut self, kind: CallKind, len: usize, build: F) -> eyre::Result<()>
+    where
+        F: Fn(Range<usize>) -> TxRequest,
+    {
+        if len == 0 {
+            return Ok(());
+        }
 
-        if !submit_margin_receipt.status() {
-            bail!("Failed to submit margin: {:?}", submit_margin_receipt)
+        let mut size = self.chunk_size(kind, len, &build).await;
+        let mut ranges = split_range(0..len, size);
+        let mut shrunk = false;
+        let mut largest_ok = 0;
+
+        while !ranges.is_empty() {
+            let txs = ranges
+                .iter()
+                .map(|range| build(range.clone()))
+                .collect_vec();
+
+            let results = self.tx_manager.send_each(txs).await;
+
+            let mut retry_ranges = Vec::new();
+            for (range, result) in ranges.into_iter().zip(results) {
+                match result {
+                    Some(Ok(_)) => largest_ok = largest_ok.max(range.len()),
+                    None => retry_ranges.extend(split_range(range, size)),
+                    Some(Err(err)) if is_out_of_gas(&err) => {
+                        let Some(smaller) = self.chunk_sizes.shrink(kind, range.len()) else {
+                            return Err(err)
+                                .with_context(|| format!("Single asset {} ran out of gas", kind));
+                        };
+                        warn!(%kind, chunk_size = %smaller, "⛽ Chunk ran out of gas, shrinking");
+                        size = size.min(smaller);
+                        shrunk = true;
+                        retry_ranges.extend(split_range(range, smaller));
+                    }
+                    Some(Err(err)) => return Err(err),
+                }
+            }
+
+            ranges = retry_ranges;
         }
 
-        debug!("Submit margin receipt: {:?}", submit_margin_receipt);
+        // Chunk size shrunk in the past is tried larger again, as long as chunks fit
+        if !shrunk && let Some(larger) = self.chunk_sizes.grow(kind, largest_ok) {
+            debug!(%kind, chunk_size = %larger, "⛽ Chunks fit gas, growing");
+        }
 
         Ok(())
     }
 
     pub async fn update_market(&mut self, assets: &Labels) -> eyre::Result<()> {
         info!("📈 Handle: UpdateMarket");
-        for chunk in assets.data.chunks(self.chunk_size) {
-            self._update_market(chunk).await?;
+
+        let market_data = self
+            .market_data
+            .market_data(&assets.data)
+            .context("Failed to obtain market data")?;
+
+        if market_data.len() != assets.data.len() {
+            bail!(
+                "Market data source returned {} entries for {} assets",
+                market_data.len(),
+                assets.data.len()
+            )
         }
-        Ok(())
-    }
 
-    async fn _update_market(&mut self, assets: &[u128]) -> eyre::Result<()> {
-        let banker = IBanker::new(self.castle_address, &self.provider);
+        let banker = IBanker::new(self.castle_address, self.provider.clone());
+        let vendor_id = self.vendor_id;
 
-        let mut price_gen = ValueGen::new(100_00, 1000_00, 2);
-        let prices = Vector {
-            data: assets.iter().map(|_| price_gen.next()).collect_vec(),
-        };
+        info!("Submitting market data...");
+        self.submit_chunked(CallKind::SubmitMarketData, assets.data.len(), |range| {
+            let chunk = &market_data[range.clone()];
 
-        let mut slope_gen = ValueGen::new(0_01, 0_10, 2);
-        let slopes = Vector {
-            data: assets.iter().map(|_| slope_gen.next()).collect_vec(),
-        };
+            let prices = Vector {
+                data: chunk.iter().map(|x| x.price).collect_vec(),
+            };
 
-        let mut liquidity_gen = ValueGen::new(0_10, 10_00, 2);
-        let liquidity = Vector {
-            data: assets.iter().map(|_| liquidity_gen.next()).collect_vec(),
-        };
+            let slopes = Vector {
+                data: chunk.iter().map(|x| x.slope).collect_vec(),
+            };
 
-        let asset_names = Labels::from_vec_u128(assets.to_vec());
+            let liquidity = Vector {
+                data: chunk.iter().map(|x| x.liquidity).collect_vec(),
+            };
 
-        info!("Submitting market data...");
-        let submit_market_data = banker
-            .submitMarketData(
-                self.vendor_id,
+            let asset_names = Labels::from_vec_u128(assets.data[range].to_vec());
+
+            TxRequest::from_call(banker.submitMarketData(
+                vendor_id,
                 asset_names.to_vec().into(),
                 liquidity.to_vec().into(),
                 prices.to_vec().into(),
                 slopes.to_vec().into(),
-            )
-            .send()
-            .await
-            .context("")?;
-
-        let submit_market_data_receipt = submit_market_data
-            .get_receipt()
-            .await
-            .context("Failed to confirm submit market data")?;
-
-        if !submit_market_data_receipt.status() {
-            bail!(
-                "Failed to submit market data: {:?}",
-                submit_market_data_receipt
-            )
+            ))
+        })
+        .await
+        .context("Failed to submit market data")?;
+
+        self.venue.update_market(&assets.data, &market_data);
+        // Simulated market data was never submitted, so it is not a mark price
+        if !self.tx_manager.is_dry_run()
+            && let Err(err) = self.ledger.record_marks(&assets.data, &market_data)
+        {
+            warn!("Failed to record mark prices: {:?}", err);
         }
 
-        debug!("Submit market receipt: {:?}", submit_market_data_receipt);
-
         Ok(())
     }
 
-    pub async fn update_supply(&mut self) -> eyre::Result<()> {
-        info!("🚛 Handle: UpdateSupply");
+    pub async fn get_demand(&self) -> eyre::Result<VendorDemand> {
         let steward = ISteward::new(self.castle_address, &self.provider);
 
         let demand_bytes = steward
             .getVendorDemand(self.vendor_id)
             .call()
             .await
-            .context("Faile to obtain demand")?;
-
-        let demand_long = Vector::from_vec(demand_bytes[DEMAND_LONG_OFFSET].to_vec());
-        let demand_short = Vector::from_vec(demand_bytes[DEMAND_SHORT_OFFSET].to_vec());
-
-        let zipped = self
-            .market_assets
-            .data
-            .iter()
-            .zip(demand_long.data.iter())
-            .zip(demand_short.data.iter())
-            .map(|((a, b), c)| (*a, *b, *c))
-            .collect_vec();
-
-        for chunk in zipped.chunks(self.chunk_size) {
-            let assets_chunk = chunk.iter().map(|(a, _, _)| *a).collect_vec();
-            let demand_long_chunk = chunk.iter().map(|(_, b, _)| *b).collect_vec();
-            let demand_short_chunk = chunk.iter().map(|(_, _, c)| *c).collect_vec();
-
-            self._submit_supply(
-                Labels { data: assets_chunk },
-                Vector {
-                    data: demand_long_chunk,
-                },
-                Vector {
-                    data: demand_short_chunk,
-                },
-            )
-            .await?;
+            .context("Failed to obtain demand")?;
+
+        VendorDemand::try_from_bytes(self.vendor_id, &self.market_assets, &demand_bytes)
+    }
+
+    pub async fn get_supply(&self) -> eyre::Result<VendorSupply> {
+        let steward = ISteward::new(self.castle_address, &self.provider);
+
+        let supply_bytes = steward
+            .getVendorSupply(self.vendor_id)
+            .call()
+            .await
+            .context("Failed to obtain supply")?;
+
+        VendorSupply::try_from_bytes(self.vendor_id, &self.market_assets, &supply_bytes)
+    }
+
+    pub async fn get_delta(&self) -> eyre::Result<VendorDelta> {
+        let steward = ISteward::new(self.castle_address, &self.provider);
+
+        let delta_bytes = steward
+            .getVendorDelta(self.vendor_id)
+            .call()
+            .await
+            .context("Failed to obtain delta")?;
+
+        VendorDelta::try_from_bytes(self.vendor_id, &self.market_assets, &delta_bytes)
+    }
+
+    /// Demand, supply and delta of all market assets of the vendor
+    pub async fn inventory_snapshot(&self) -> eyre::Result<InventorySnapshot> {
+        let (demand, supply, delta) =
+            tokio::try_join!(self.get_demand(), self.get_supply(), self.get_delta())?;
+
+        Ok(InventorySnapshot {
+            demand,
+            supply,
+            delta,
+        })
+    }
+
+    /// Whether hedge orders are waiting for fills
+    pub fn has_pending_hedges(&self) -> bool {
+        self.hedger.has_in_flight()
+    }
+
+    pub fn get_fill_poll_interval(&self) -> Duration {
+        self.hedger.get_poll_interval()
+    }
+
+    /// Hedge delta of vendor assets, and submit supply resulting from fills
+    pub async fn update_supply(&mut self) -> eyre::Result<()> {
+        info!("🚛 Handle: UpdateSupply");
+
+        if !self.hedger.is_loaded() {
+            let supply = self.get_supply().await?;
+            self.hedger.load_supply(&supply.quantities);
         }
 
-        Ok(())
+        let delta = self.get_delta().await?;
+
+        let mut orders = self.hedger.plan(&delta.quantities).into_iter();
+        while let Some(order) = orders.next() {
+            info!(
+                order_id = %order.order_id,
+                asset = %order.asset,
+                side = %order.side,
+                quantity = %order.quantity,
+                "🛡️ Hedge"
+            );
+            let order_id = order.order_id;
+            if let Err(err) = self.venue.submit(order) {
+                // Orders not submitted are planned again next time
+                self.hedger.cancel(order_id);
+                for order in orders {
+                    self.hedger.cancel(order.order_id);
+                }
+                return Err(err).context("Failed to submit hedge order");
+            }
+        }
+
+        self.poll_fills().await
     }
 
-    async fn _submit_supply(
-        &mut self,
-        assets: Labels,
-        supply_long: Vector,
-        supply_short: Vector,
-    ) -> eyre::Result<()> {
-        let banker = IBanker::new(self.castle_address, &self.provider);
+    /// Apply fills reported by venue, and submit supply of assets that changed
+    pub async fn poll_fills(&mut self) -> eyre::Result<()> {
+        let fills = self.venue.poll_fills().context("Failed to poll fills")?;
+        // Simulated supply is never submitted, so nothing is persisted to the ledger
+        let dry_run = self.tx_manager.is_dry_run();
+
+        for fill in &fills {
+            self.hedger.apply_fill(fill);
+            if !dry_run && let Err(err) = self.ledger.record_fill(fill) {
+                warn!(order_id = %fill.order_id, "Failed to record fill: {:?}", err);
+            }
+            let position = self.hedger.get_position(fill.asset);
+            info!(
+                order_id = %fill.order_id,
+                asset = %fill.asset,
+                side = %fill.side,
+                requested = %fill.requested,
+                quantity = %fill.quantity,
+                price = %fill.price,
+                position = %position,
+                "🤝 Fill"
+            );
+        }
+
+        let supply = self.hedger.pending_supply();
+        if supply.is_empty() {
+            return Ok(());
+        }
 
-        info!("Submitting supply...");
-        let submit_supply = banker
-            .submitSupply(
-                self.vendor_id,
+        let banker = IBanker::new(self.castle_address, self.provider.clone());
+        let vendor_id = self.vendor_id;
+
+        info!(assets = %supply.len(), "Submitting supply...");
+        self.submit_chunked(CallKind::SubmitSupply, supply.len(), |range| {
+            let chunk = &supply[range];
+            let assets = Labels {
+                data: chunk.iter().map(|(asset, _)| *asset).collect_vec(),
+            };
+            let supply_long = Vector {
+                data: chunk.iter().map(|(_, x)| x.long).collect_vec(),
+            };
+            let supply_short = Vector {
+                data: chunk.iter().map(|(_, x)| x.short).collect_vec(),
+            };
+
+            TxRequest::from_call(banker.submitSupply(
+                vendor_id,
                 assets.to_vec().into(),
                 supply_short.to_vec().into(),
                 supply_long.to_vec().into(),
-            )
-            .send()
-            .await
-            .context("Failed to submit supply")?;
-
-        let submit_supply_receipt = submit_supply
-            .get_receipt()
-            .await
-            .context("Failed to confirm submit supply")?;
+            ))
+        })
+        .await
+        .context("Failed to submit supply")?;
 
-        if !submit_supply_receipt.status() {
-            bail!("Failed to submit supply: {:?}", submit_supply_receipt)
+        if dry_run {
+            return Ok(());
         }
 
-        debug!("Submit supply receipt: {:?}", submit_supply_receipt);
+        self.hedger.mark_submitted(&supply);
+        if let Err(err) = self.ledger.record_supply(&supply) {
+            warn!("Failed to record supply: {:?}", err);
+        }
 
         Ok(())
     }
 }
+
+/// Vendors run by this process, each with its own signer, keyed by Vendor id
+pub struct Vendors<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    vendors: BTreeMap<u128, Vendor<P>>,
+}
+
+impl<P> Vendors<P>
+where
+    P: Provider + WalletProvider + Clone + 'static,
+{
+    pub fn new(vendors: Vec<Vendor<P>>) -> eyre::Result<Self> {
+        if vendors.is_empty() {
+            bail!("No Vendor to run")
+        }
+        let count = vendors.len();
+        let vendors: BTreeMap<_, _> = vendors.into_iter().map(|x| (x.vendor_id, x)).collect();
+        if vendors.len() != count {
+            bail!("Vendor is given more than once")
+        }
+        Ok(Self { vendors })
+    }
+
+    pub fn get(&self, vendor_id: u128) -> Option<&Vendor<P>> {
+        self.vendors.get(&vendor_id)
+    }
+
+    pub fn get_mut(&mut self, vendor_id: u128) -> Option<&mut Vendor<P>> {
+        self.vendors.get_mut(&vendor_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Vendor<P>> {
+        self.vendors.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Vendor<P>> {
+        self.vendors.values_mut()
+    }
+
+    pub fn has_pending_hedges(&self) -> bool {
+        self.vendors.values().any(|x| x.has_pending_hedges())
+    }
+
+    /// Shortest of fill poll intervals of all Vendors
+    pub fn get_fill_poll_interval(&self) -> Duration {
+        self.vendors
+            .values()
+            .map(|x| x.get_fill_poll_interval())
+            .min()
+            .unwrap_or(Duration::from_secs(1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::{
+        network::EthereumWallet, primitives::Bytes, providers::ProviderBuilder,
+        signers::local::PrivateKeySigner, sol_types::SolCall, transports::mock::Asserter,
+    };
+
+    use super::*;
+    use crate::{
+        common::amount::Amount,
+        config::TxConfig,
+        hedging::HedgingConfig,
+        market::random::RandomMarketData,
+        venue::{
+            execution::{Fill, Side},
+            instant::InstantVenue,
+        },
+    };
+
+    /// Vendor simulating its transactions against mocked provider
+    fn dry_run_vendor(
+        ledger: Ledger,
+    ) -> (
+        Vendor<impl Provider + WalletProvider + Clone + 'static>,
+        Asserter,
+    ) {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(PrivateKeySigner::random()))
+            .connect_mocked_client(asserter.clone());
+
+        let tx_config = TxConfig {
+            dry_run: true,
+            ..Default::default()
+        };
+        let vendor = Vendor::new(
+            provider.clone(),
+            TxManager::new(provider, tx_config),
+            Address::ZERO,
+            Address::ZERO,
+            Address::ZERO,
+            1,
+            500,
+            ChunkingConfig {
+                auto: false,
+                ..Default::default()
+            },
+            Box::new(RandomMarketData::new()),
+            Box::new(InstantVenue::new()),
+            Hedger::new(&HedgingConfig::default()),
+            ledger,
+        );
+        (vendor, asserter)
+    }
+
+    #[tokio::test]
+    async fn test_vendor_setup() {
+        let (mut vendor, asserter) = dry_run_vendor(Ledger::new(None));
+
+        let config = VendorConfig {
+            market_size: 3,
+            ..Default::default()
+        };
+
+        // Supply and delta of all three assets
+        let zeros = Bytes::from(
+            Vector {
+                data: vec![Amount::ZERO; 3],
+            }
+            .to_vec(),
+        );
+        let vectors = vec![zeros.clone(), zeros];
+
+        // Simulated submitAssets, submitMargin and submitMarketData
+        for _ in 0..3 {
+            asserter.push_success(&Bytes::new());
+        }
+        asserter.push_success(&Bytes::from(
+            ISteward::getVendorSupplyCall::abi_encode_returns(&vectors),
+        ));
+        asserter.push_success(&Bytes::from(
+            ISteward::getVendorDeltaCall::abi_encode_returns(&vectors),
+        ));
+
+        vendor.setup(&config).await.unwrap();
+        assert_eq!(vendor.get_market_assets().data, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_vendor_dry_run_marks() {
+        let path =
+            std::env::temp_dir().join(format!("ledger-{}-dry-run.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // Asset 1 is in the ledger, so its price would be recorded as mark
+        let mut ledger = Ledger::open(Some(path.clone())).unwrap();
+        ledger
+            .record_fill(&Fill {
+                order_id: 1,
+                asset: 1,
+                side: Side::Buy,
+                requested: Amount::ONE,
+                quantity: Amount::ONE,
+                price: Amount::ONE,
+            })
+            .unwrap();
+        let journal = std::fs::read_to_string(&path).unwrap();
+
+        let (mut vendor, asserter) = dry_run_vendor(Ledger::open(Some(path.clone())).unwrap());
+
+        // Simulated submitMarketData
+        asserter.push_success(&Bytes::new());
+
+        vendor
+            .update_market(&Labels { data: vec![1, 2] })
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), journal);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::amount::Amount,
+    market::source::MarketData,
+    venue::{instant::InstantVenue, simulated::SimulatedVenue},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Buy => write!(f, "buy"),
+            Side::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+/// Order hedging vendor inventory of single asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HedgeOrder {
+    pub order_id: u64,
+    pub asset: u128,
+    pub side: Side,
+    pub quantity: Amount,
+}
+
+/// Execution of an order reported by the venue
+///
+/// Each order gets exactly one fill, and part of the order left unfilled is
+/// cancelled (so that next hedge is planned from up to date delta).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fill {
+    pub order_id: u64,
+    pub asset: u128,
+    pub side: Side,
+    /// Quantity of the order
+    pub requested: Amount,
+    /// Quantity actually filled, possibly zero
+    pub quantity: Amount,
+    /// Average price of the filled quantity
+    pub price: Amount,
+}
+
+/// Where Vendor executes orders hedging its inventory
+pub trait ExecutionVenue: Send {
+    /// Market data as last submitted by Vendor via `IBanker::submitMarketData`
+    fn update_market(&mut self, assets: &[u128], market_data: &[MarketData]);
+
+    /// Accept order, which is filled later
+    fn submit(&mut self, order: HedgeOrder) -> eyre::Result<()>;
+
+    /// Fills of orders, which became available since last poll
+    fn poll_fills(&mut self) -> eyre::Result<Vec<Fill>>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum VenueConfig {
+    /// Fill every order immediately at market price
+    #[default]
+    Instant,
+    /// Local exchange with latency, partial fills and slippage
+    Simulated {
+        /// Delay between submitting order and its fill
+        #[serde(default = "default_latency_ms")]
+        latency_ms: u64,
+        /// Largest part of asset liquidity filled by single order
+        #[serde(default = "default_max_liquidity_percent")]
+        max_liquidity_percent: u64,
+    },
+}
+
+fn default_latency_ms() -> u64 {
+    500
+}
+
+fn default_max_liquidity_percent() -> u64 {
+    100
+}
+
+impl VenueConfig {
+    pub fn build(&self) -> Box<dyn ExecutionVenue> {
+        match self {
+            VenueConfig::Instant => Box::new(InstantVenue::new()),
+            VenueConfig::Simulated {
+                latency_ms,
+                max_liquidity_percent,
+            } => Box::new(SimulatedVenue::new(
+                std::time::Duration::from_millis(*latency_ms),
+                *max_liquidity_percent,
+            )),
+        }
+    }
+}
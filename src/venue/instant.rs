@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::{
+    common::amount::Amount,
+    market::source::MarketData,
+    venue::execution::{ExecutionVenue, Fill, HedgeOrder},
+};
+
+/// Fills every order in full at market price, as soon as it is polled
+///
+/// Orders of assets without market data are kept until their price is known.
+#[derive(Default)]
+pub struct InstantVenue {
+    prices: HashMap<u128, Amount>,
+    orders: Vec<HedgeOrder>,
+}
+
+impl InstantVenue {
+    pub fn new() -> Self {
+        Self {
+            prices: HashMap::new(),
+            orders: Vec::new(),
+        }
+    }
+}
+
+impl ExecutionVenue for InstantVenue {
+    fn update_market(&mut self, assets: &[u128], market_data: &[MarketData]) {
+        for (asset, data) in assets.iter().zip(market_data) {
+            self.prices.insert(*asset, data.price);
+        }
+    }
+
+    fn submit(&mut self, order: HedgeOrder) -> eyre::Result<()> {
+        self.orders.push(order);
+        Ok(())
+    }
+
+    fn poll_fills(&mut self) -> eyre::Result<Vec<Fill>> {
+        let (priced, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition(|order| self.prices.contains_key(&order.asset));
+        self.orders = waiting;
+
+        Ok(priced
+            .into_iter()
+            .map(|order| Fill {
+                order_id: order.order_id,
+                asset: order.asset,
+                side: order.side,
+                requested: order.quantity,
+                quantity: order.quantity,
+                price: self.prices[&order.asset],
+            })
+            .collect_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::venue::execution::Side;
+
+    #[test]
+    fn test_instant_venue_waits_for_price() {
+        let mut venue = InstantVenue::new();
+        venue
+            .submit(HedgeOrder {
+                order_id: 1,
+                asset: 101,
+                side: Side::Buy,
+                quantity: "2".parse().unwrap(),
+            })
+            .unwrap();
+
+        // No price yet, so order is not filled at zero
+        assert!(venue.poll_fills().unwrap().is_empty());
+
+        venue.update_market(
+            &[101],
+            &[MarketData {
+                price: "100".parse().unwrap(),
+                slope: "0.1".parse().unwrap(),
+                liquidity: "10".parse().unwrap(),
+            }],
+        );
+
+        let fills = venue.poll_fills().unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].price, "100".parse().unwrap());
+        assert!(venue.poll_fills().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    common::amount::Amount,
+    market::source::MarketData,
+    venue::execution::{ExecutionVenue, Fill, HedgeOrder, Side},
+};
+
+/// Local exchange filling orders after latency, against market data submitted by Vendor
+///
+/// Each order takes at most `max_liquidity_percent` of asset liquidity, and
+/// liquidity taken is not available to next orders until market data is
+/// updated, so that large orders are filled partially. Price moves against
+/// the order linearly with the part of liquidity taken, by `slope` when all
+/// of it is taken, and order is filled at the average price.
+pub struct SimulatedVenue {
+    latency: Duration,
+    max_liquidity_percent: u64,
+    market: HashMap<u128, MarketData>,
+    /// Liquidity not yet taken by orders since last market update
+    available: HashMap<u128, Amount>,
+    orders: VecDeque<(Instant, HedgeOrder)>,
+}
+
+impl SimulatedVenue {
+    pub fn new(latency: Duration, max_liquidity_percent: u64) -> Self {
+        Self {
+            latency,
+            max_liquidity_percent: max_liquidity_percent.min(100),
+            market: HashMap::new(),
+            available: HashMap::new(),
+            orders: VecDeque::new(),
+        }
+    }
+
+    fn fill(&mut self, order: HedgeOrder) -> Fill {
+        let (quantity, price) = match self.market.get(&order.asset) {
+            Some(market) => {
+                let available = self
+                    .available
+                    .get(&order.asset)
+                    .copied()
+                    .unwrap_or(Amount::ZERO);
+                let max_quantity = Amount::from_u128_raw(
+                    market.liquidity.to_u128_raw() / 100 * self.max_liquidity_percent as u128,
+                );
+                let quantity = order.quantity.min(available).min(max_quantity);
+                let price = slipped_price(market, order.side, quantity);
+
+                self.available.insert(
+                    order.asset,
+                    available.saturating_sub(quantity).unwrap_or(Amount::ZERO),
+                );
+
+                (quantity, price)
+            }
+            None => (Amount::ZERO, Amount::ZERO),
+        };
+
+        Fill {
+            order_id: order.order_id,
+            asset: order.asset,
+            side: order.side,
+            requested: order.quantity,
+            quantity,
+            price,
+        }
+    }
+
+    /// Fills of orders, whose latency elapsed by given time
+    pub fn poll_fills_at(&mut self, now: Instant) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        while let Some((due, _)) = self.orders.front() {
+            if now < *due {
+                break;
+            }
+            if let Some((_, order)) = self.orders.pop_front() {
+                fills.push(self.fill(order));
+            }
+        }
+        fills
+    }
+}
+
+/// Average price of given quantity, after price impact
+fn slipped_price(market: &MarketData, side: Side, quantity: Amount) -> Amount {
+    let impact = (|| {
+        market
+            .slope
+            .checked_mul(quantity)?
+            .checked_div(market.liquidity)?
+            .checked_div(Amount::TWO)
+    })()
+    .unwrap_or(Amount::ZERO);
+
+    let factor = match side {
+        Side::Buy => Amount::ONE.checked_add(impact),
+        Side::Sell => Amount::ONE.saturating_sub(impact),
+    };
+
+    factor
+        .and_then(|factor| market.price.checked_mul(factor))
+        .unwrap_or(market.price)
+}
+
+impl ExecutionVenue for SimulatedVenue {
+    fn update_market(&mut self, assets: &[u128], market_data: &[MarketData]) {
+        for (asset, data) in assets.iter().zip(market_data) {
+            self.market.insert(*asset, *data);
+            self.available.insert(*asset, data.liquidity);
+        }
+    }
+
+    fn submit(&mut self, order: HedgeOrder) -> eyre::Result<()> {
+        self.orders
+            .push_back((Instant::now() + self.latency, order));
+        Ok(())
+    }
+
+    fn poll_fills(&mut self) -> eyre::Result<Vec<Fill>> {
+        Ok(self.poll_fills_at(Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_simulated_venue() {
+        let mut venue = SimulatedVenue::new(Duration::from_secs(60), 50);
+        venue.update_market(
+            &[101],
+            &[MarketData {
+                price: "100".parse().unwrap(),
+                slope: "0.1".parse().unwrap(),
+                liquidity: "10".parse().unwrap(),
+            }],
+        );
+
+        let order = |order_id, side, quantity: &str| HedgeOrder {
+            order_id,
+            asset: 101,
+            side,
+            quantity: quantity.parse().unwrap(),
+        };
+        venue.submit(order(1, Side::Buy, "8")).unwrap();
+        venue.submit(order(2, Side::Sell, "2")).unwrap();
+        venue.submit(order(3, Side::Buy, "5")).unwrap();
+
+        assert!(venue.poll_fills().unwrap().is_empty());
+
+        let fills = venue.poll_fills_at(Instant::now() + Duration::from_secs(61));
+        assert_eq!(fills.len(), 3);
+
+        // Capped at half of liquidity, with price moved by half of slope * 5 / 10
+        assert_eq!(fills[0].quantity, "5".parse().unwrap());
+        assert_eq!(fills[0].price, "102.5".parse().unwrap());
+
+        assert_eq!(fills[1].quantity, "2".parse().unwrap());
+        assert_eq!(fills[1].price, "99".parse().unwrap());
+
+        // Only what is left of liquidity
+        assert_eq!(fills[2].quantity, "3".parse().unwrap());
+        assert_eq!(fills[2].requested, "5".parse().unwrap());
+    }
+}